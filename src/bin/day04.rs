@@ -1,5 +1,6 @@
 extern crate core;
 
+use advent_of_code_2022::interval_set::{Interval, IntervalSet};
 use std::error::Error;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
@@ -66,12 +67,16 @@ impl FromStr for SectionAssignment {
 }
 
 impl SectionAssignment {
+    fn sections(&self) -> IntervalSet {
+        IntervalSet::from(Interval::new(self.start as i64, self.end as i64))
+    }
+
     fn contains(&self, other: &SectionAssignment) -> bool {
-        other.start >= self.start && other.end <= self.end
+        self.sections().is_superset(&other.sections())
     }
 
     fn overlaps(&self, other: &SectionAssignment) -> bool {
-        other.end >= self.start && other.start <= self.end
+        !self.sections().intersection(&other.sections()).is_empty()
     }
 }
 
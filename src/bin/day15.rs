@@ -1,3 +1,4 @@
+use advent_of_code_2022::interval_set::{Interval, IntervalSet};
 use lazy_static::lazy_static;
 use regex::Regex;
 use std::collections::HashSet;
@@ -47,81 +48,34 @@ impl SensorArray {
             .collect()
     }
 
-    fn covered_ranges(&self, y: i32) -> Vec<Range> {
-        let mut ranges: Vec<Range> = self
-            .sensors
+    fn covered_ranges(&self, y: i32) -> IntervalSet {
+        self.sensors
             .iter()
             .filter_map(|sensor| sensor.covered_range(y))
-            .collect();
-
-        ranges.sort_by_key(|range| range.start);
-
-        let mut merged_ranges: Vec<Range> = vec![];
-
-        for range in ranges {
-            if let Some(merged_range) = merged_ranges
-                .last()
-                .and_then(|tail_range| tail_range.union(&range))
-            {
-                merged_ranges.pop();
-                merged_ranges.push(merged_range);
-            } else {
-                merged_ranges.push(range);
-            }
-        }
-
-        merged_ranges
+            .collect()
     }
 
-    fn positions_without_beacon(&self, y: i32) -> u32 {
-        self.covered_ranges(y)
-            .iter()
-            .map(|range| range.span())
-            .sum::<u32>()
+    fn positions_without_beacon(&self, y: i32) -> u64 {
+        self.covered_ranges(y).len()
             - self
                 .beacon_positions()
                 .iter()
                 .filter(|(_, beacon_y)| *beacon_y == y)
-                .count() as u32
+                .count() as u64
     }
 
     fn tuning_frequency(&self, max_coordinate: i32) -> Option<u64> {
-        let bounds = Range::new(0, max_coordinate);
+        let bounds = Interval::new(0, max_coordinate as i64);
 
         for y in 0..=max_coordinate {
-            let covered_ranges = self.covered_ranges(y);
+            let uncovered_ranges = self.covered_ranges(y).complement(bounds);
 
-            let scanned_spaces: u32 = covered_ranges
-                .iter()
-                .filter_map(|range| {
-                    range
-                        .intersection(&bounds)
-                        .map(|intersection| intersection.span())
-                })
-                .sum();
-
-            // A more verbose way to write this would be:
-            //
-            // ```
-            // let desired_positions_without_beacon = Range::new(0, max_coordinate).span() - 1;
-            // ```
-            //
-            // …but that works out to just be `max_coordinate` in practice.
-            if scanned_spaces == max_coordinate as u32 {
-                let x = if covered_ranges[0].start == 1 {
-                    // Literal edge case; the only uncovered spot is at x = 0
-                    0
-                } else if covered_ranges[covered_ranges.len() - 1].end == max_coordinate - 1 {
-                    // Edge case on the far end; the only uncovered spot is at the very end of the
-                    // row
-                    max_coordinate - 1
-                } else {
-                    covered_ranges
-                        .windows(2)
-                        .find(|pair| pair[1].start == pair[0].end + 2)
-                        .map(|pair| pair[0].end + 1)
-                        .expect("Should have two intervals with a one-space gap")
-                };
+            if uncovered_ranges.len() == 1 {
+                let x = uncovered_ranges
+                    .intervals()
+                    .next()
+                    .expect("Should have exactly one uncovered interval")
+                    .start;
 
                 return Some((x as u64 * 4000000) + y as u64);
             }
@@ -174,49 +128,13 @@ impl Sensor {
         self.x.abs_diff(self.closest_beacon_x) + self.y.abs_diff(self.closest_beacon_y)
     }
 
-    fn covered_range(&self, y: i32) -> Option<Range> {
+    fn covered_range(&self, y: i32) -> Option<Interval> {
         if self.y.abs_diff(y) <= self.radius() {
-            Some(Range {
-                start: self.x - (self.radius() - self.y.abs_diff(y)) as i32,
-                end: self.x + (self.radius() - self.y.abs_diff(y)) as i32,
-            })
-        } else {
-            None
-        }
-    }
-}
-
-#[derive(Debug, Eq, PartialEq)]
-struct Range {
-    start: i32,
-    end: i32,
-}
-
-impl Range {
-    fn new(start: i32, end: i32) -> Self {
-        Range { start, end }
-    }
-
-    fn span(&self) -> u32 {
-        self.end.abs_diff(self.start) + 1
-    }
-
-    fn union(&self, other: &Self) -> Option<Self> {
-        if other.end >= self.start && other.start <= self.end {
-            Some(Range::new(
-                self.start.min(other.start),
-                self.end.max(other.end),
-            ))
-        } else {
-            None
-        }
-    }
+            let half_width = (self.radius() - self.y.abs_diff(y)) as i64;
 
-    fn intersection(&self, other: &Self) -> Option<Self> {
-        if other.end >= self.start && other.start <= self.end {
-            Some(Range::new(
-                self.start.max(other.start),
-                self.end.min(other.end),
+            Some(Interval::new(
+                self.x as i64 - half_width,
+                self.x as i64 + half_width,
             ))
         } else {
             None
@@ -267,8 +185,8 @@ mod test {
         let sensor =
             Sensor::from_str("Sensor at x=8, y=7: closest beacon is at x=2, y=10").unwrap();
 
-        assert_eq!(Some(Range::new(-1, 17)), sensor.covered_range(7));
-        assert_eq!(Some(Range::new(8, 8)), sensor.covered_range(16));
+        assert_eq!(Some(Interval::new(-1, 17)), sensor.covered_range(7));
+        assert_eq!(Some(Interval::new(8, 8)), sensor.covered_range(16));
         assert_eq!(None, sensor.covered_range(17));
     }
 
@@ -3,42 +3,75 @@ extern crate core;
 use itertools::Itertools;
 use lazy_static::lazy_static;
 use regex::Regex;
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 use std::error::Error;
+use std::fmt::{Display, Formatter};
 use std::fs;
 use std::str::FromStr;
 
+const DEFAULT_START: &str = "AA";
+
 fn main() -> Result<(), Box<dyn Error>> {
     let args: Vec<String> = std::env::args().collect();
 
     if let Some(path) = args.get(1) {
         let mut volcano = Volcano::from_str(fs::read_to_string(path)?.as_str())?;
 
-        println!(
-            "Maximum pressure released over 30 minutes with 1 actor: {}",
-            volcano.maximum_pressure_release(1, 30)
-        );
+        if let Some(dot_path) = args.get(2) {
+            fs::write(dot_path, volcano.to_dot())?;
+        }
 
-        println!(
-            "Maximum pressure released over 26 minutes with 2 actors: {}",
-            volcano.maximum_pressure_release(2, 26)
-        );
+        {
+            let plan = volcano.optimal_plan(1, 30);
+
+            println!(
+                "Maximum pressure released over 30 minutes with 1 actor: {}",
+                plan.pressure_released()
+            );
+            println!("\n{}", plan);
+        }
+
+        {
+            let plan = volcano.optimal_plan(2, 26);
+
+            println!(
+                "Maximum pressure released over 26 minutes with 2 actors: {}",
+                plan.pressure_released()
+            );
+            println!("\n{}", plan);
+        }
 
         Ok(())
     } else {
-        Err("Usage: day16 INPUT_FILE_PATH".into())
+        Err("Usage: day16 INPUT_FILE_PATH [DOT_OUTPUT_PATH]".into())
     }
 }
 
 struct Volcano {
-    flow_rates: HashMap<String, u32>,
-    travel_times: HashMap<(String, String), u32>,
+    valve_names: Vec<String>,
+    flow_rates: Vec<u32>,
+    connections: Vec<Vec<usize>>,
+    travel_times: Vec<Vec<u32>>,
+
+    start: usize,
+
+    // Indices of the valves with a non-zero flow rate; the valve at `useful_valves[i]` corresponds
+    // to bit `i` in sets of opened valves
+    useful_valves: Vec<usize>,
+
+    subset_pressure_cache: HashMap<u32, Vec<u32>>,
 }
 
 impl FromStr for Volcano {
     type Err = Box<dyn Error>;
 
     fn from_str(string: &str) -> Result<Self, Self::Err> {
+        Volcano::with_start_valve(string, DEFAULT_START)
+    }
+}
+
+impl Volcano {
+    fn with_start_valve(string: &str, start: &str) -> Result<Self, Box<dyn Error>> {
         lazy_static! {
             static ref VALVE_PATTERN: Regex = Regex::new(
                 r"Valve ([A-Z]+) has flow rate=(\d+); (?:tunnel|tunnels) (?:lead|leads) to (?:valve|valves) (.+)"
@@ -46,239 +79,489 @@ impl FromStr for Volcano {
             .unwrap();
         }
 
-        let mut flow_rates = HashMap::new();
-        let mut connections = HashMap::new();
+        let mut valve_names = vec![];
+        let mut flow_rates = vec![];
+        let mut destination_names = vec![];
 
         for line in string.lines().filter(|line| !line.is_empty()) {
             if let Some(captures) = VALVE_PATTERN.captures(line) {
-                let valve = captures[1].to_string();
-                let flow_rate = captures[2].parse()?;
-                let destinations: Vec<String> = captures[3]
-                    .split(", ")
-                    .map(|connection| connection.to_string())
-                    .collect();
-
-                flow_rates.insert(valve.clone(), flow_rate);
-                connections.insert(valve, destinations);
+                valve_names.push(captures[1].to_string());
+                flow_rates.push(captures[2].parse()?);
+                destination_names.push(
+                    captures[3]
+                        .split(", ")
+                        .map(|destination| destination.to_string())
+                        .collect::<Vec<String>>(),
+                );
             } else {
                 return Err("Could not parse line".into());
             }
         }
 
-        // Precalculate travel times
-        let valves = {
-            let mut valves: Vec<String> = flow_rates
-                .iter()
-                .filter_map(|(valve, flow_rate)| {
-                    if *flow_rate > 0 {
-                        Some(valve.clone())
-                    } else {
-                        None
-                    }
-                })
-                .collect();
+        let valve_indices: HashMap<&str, usize> = valve_names
+            .iter()
+            .enumerate()
+            .map(|(index, valve)| (valve.as_str(), index))
+            .collect();
+
+        if valve_indices.len() != valve_names.len() {
+            return Err("Valves must not be defined more than once".into());
+        }
+
+        let connections = destination_names
+            .iter()
+            .map(|destinations| {
+                destinations
+                    .iter()
+                    .map(|destination| {
+                        valve_indices
+                            .get(destination.as_str())
+                            .copied()
+                            .ok_or_else(|| format!("Unknown valve: {}", destination).into())
+                    })
+                    .collect::<Result<Vec<usize>, Box<dyn Error>>>()
+            })
+            .collect::<Result<Vec<Vec<usize>>, _>>()?;
+
+        let start = *valve_indices
+            .get(start)
+            .ok_or_else(|| format!("Start valve not found: {}", start))?;
+
+        let travel_times = Volcano::shortest_paths(&connections);
+
+        let useful_valves: Vec<usize> = flow_rates
+            .iter()
+            .enumerate()
+            .filter(|(_, flow_rate)| **flow_rate > 0)
+            .map(|(valve, _)| valve)
+            .collect();
+
+        if let Some(&unreachable_valve) = useful_valves
+            .iter()
+            .find(|&&valve| travel_times[start][valve] == u32::MAX)
+        {
+            return Err(format!(
+                "Valve {} cannot be reached from start valve {}",
+                valve_names[unreachable_valve], valve_names[start]
+            )
+            .into());
+        }
 
-            if !valves.contains(&"AA".to_string()) {
-                valves.push("AA".to_string());
+        Ok(Volcano {
+            valve_names,
+            flow_rates,
+            connections,
+            travel_times,
+            start,
+            useful_valves,
+            subset_pressure_cache: HashMap::new(),
+        })
+    }
+
+    // Floyd-Warshall; unreachable pairs of valves have a travel time of `u32::MAX`
+    fn shortest_paths(connections: &[Vec<usize>]) -> Vec<Vec<u32>> {
+        let mut travel_times = vec![vec![u32::MAX; connections.len()]; connections.len()];
+
+        for (valve, neighbors) in connections.iter().enumerate() {
+            travel_times[valve][valve] = 0;
+
+            for &neighbor in neighbors {
+                travel_times[valve][neighbor] = travel_times[valve][neighbor].min(1);
             }
+        }
 
-            valves
-        };
+        for via in 0..connections.len() {
+            for start in 0..connections.len() {
+                for end in 0..connections.len() {
+                    let travel_time =
+                        travel_times[start][via].saturating_add(travel_times[via][end]);
+
+                    if travel_time < travel_times[start][end] {
+                        travel_times[start][end] = travel_time;
+                    }
+                }
+            }
+        }
+
+        travel_times
+    }
 
-        let mut travel_times = HashMap::new();
+    fn optimal_plan(&mut self, actors: u32, time_limit: u32) -> ValvePlan {
+        assert!(actors > 0, "Plans need at least one actor");
 
-        for a in 0..valves.len() - 1 {
-            for b in a + 1..valves.len() {
-                let mut tentative_distances = HashMap::from([(valves[a].to_string(), 0)]);
-                let mut visited_valves: HashSet<String> = HashSet::new();
+        let single_actor_pressure = self.subset_pressure(time_limit).to_vec();
 
-                while !visited_valves.contains(&valves[b]) {
-                    let (valve, distance) = tentative_distances
-                        .iter()
-                        .filter(|(valve, _)| !visited_valves.contains(*valve))
-                        .min_by_key(|(_, distance)| *distance)
-                        .unwrap();
+        // `combined_pressure[set]` is the most pressure any number of actors (starting with one
+        // and adding one at a time) can release if each actor opens a disjoint subset of `set`; we
+        // keep track of the subset assigned to each newly-added actor so we can reconstruct the
+        // full plan later.
+        let mut combined_pressure = single_actor_pressure.clone();
+        let mut assignments: Vec<Vec<usize>> = vec![];
 
-                    let valve = valve.clone();
-                    let distance = *distance;
+        for _ in 1..actors {
+            let mut next_combined_pressure = vec![0; combined_pressure.len()];
+            let mut assigned_subsets = vec![0; combined_pressure.len()];
 
-                    for neighbor in connections
-                        .get(&valve)
-                        .expect("Valve should have neighbors")
-                    {
-                        let neighbor_distance = tentative_distances
-                            .entry(neighbor.clone())
-                            .or_insert(u32::MAX);
+            for set in 0..combined_pressure.len() {
+                let mut subset = set;
 
-                        *neighbor_distance = (*neighbor_distance).min(distance + 1);
+                loop {
+                    let pressure = single_actor_pressure[subset] + combined_pressure[set ^ subset];
+
+                    if pressure > next_combined_pressure[set] {
+                        next_combined_pressure[set] = pressure;
+                        assigned_subsets[set] = subset;
+                    }
+
+                    if subset == 0 {
+                        break;
                     }
 
-                    visited_valves.insert(valve.clone());
+                    subset = (subset - 1) & set;
                 }
+            }
+
+            combined_pressure = next_combined_pressure;
+            assignments.push(assigned_subsets);
+        }
+
+        let mut remaining_valves = combined_pressure.len() - 1;
+        let mut actor_valves = vec![];
 
-                let distance = *tentative_distances.get(&valves[b]).unwrap();
+        for assigned_subsets in assignments.iter().rev() {
+            actor_valves.push(assigned_subsets[remaining_valves]);
+            remaining_valves ^= assigned_subsets[remaining_valves];
+        }
+
+        actor_valves.push(remaining_valves);
+        actor_valves.reverse();
+
+        let sequences: Vec<Vec<(u32, usize)>> = actor_valves
+            .iter()
+            .map(|&allowed_valves| self.best_sequence(allowed_valves, time_limit))
+            .collect();
+
+        self.plan_from_sequences(sequences.as_slice(), time_limit)
+    }
 
-                travel_times.insert((valves[a].clone(), valves[b].clone()), distance);
-                travel_times.insert((valves[b].clone(), valves[a].clone()), distance);
+    // Returns the most pressure a single actor can release by opening only valves in a given set,
+    // indexed by that set
+    fn subset_pressure(&mut self, time_limit: u32) -> &[u32] {
+        if !self.subset_pressure_cache.contains_key(&time_limit) {
+            let mut subset_pressure = vec![0; 1 << self.useful_valves.len()];
+
+            let allowed_valves = subset_pressure.len() - 1;
+
+            self.search(time_limit, allowed_valves, |opened_valves, pressure, _| {
+                subset_pressure[opened_valves] = subset_pressure[opened_valves].max(pressure);
+            })
+            .explore(self.start, 0, 0, 0, &mut vec![]);
+
+            // So far, we only know the pressure released by opening exactly the valves in each
+            // set; an actor can also choose to open only some of them.
+            for bit in 0..self.useful_valves.len() {
+                for set in 0..subset_pressure.len() {
+                    if set & (1 << bit) != 0 {
+                        subset_pressure[set] =
+                            subset_pressure[set].max(subset_pressure[set ^ (1 << bit)]);
+                    }
+                }
             }
+
+            self.subset_pressure_cache
+                .insert(time_limit, subset_pressure);
         }
 
-        Ok(Volcano {
-            flow_rates,
-            travel_times,
+        self.subset_pressure_cache
+            .get(&time_limit)
+            .expect("Cache should contain subset pressures for time limit")
+    }
+
+    fn best_sequence(&self, allowed_valves: usize, time_limit: u32) -> Vec<(u32, usize)> {
+        let mut best_pressure = 0;
+        let mut best_sequence = vec![];
+
+        self.search(time_limit, allowed_valves, |_, pressure, sequence| {
+            if pressure > best_pressure {
+                best_pressure = pressure;
+                best_sequence = sequence.to_vec();
+            }
         })
+        .explore(self.start, 0, 0, 0, &mut vec![]);
+
+        best_sequence
     }
-}
 
-impl Volcano {
-    fn maximum_pressure_release(&mut self, actors: u32, time_limit: u32) -> u32 {
-        const START: &str = "AA";
+    fn search<V: FnMut(usize, u32, &[(u32, usize)])>(
+        &self,
+        time_limit: u32,
+        allowed_valves: usize,
+        visit: V,
+    ) -> Search<'_, V> {
+        Search {
+            travel_times: &self.travel_times,
+            flow_rates: &self.flow_rates,
+            useful_valves: &self.useful_valves,
+            time_limit,
+            allowed_valves,
+            visit,
+        }
+    }
 
-        let mut unopened_valves: HashSet<String> = self
-            .flow_rates
+    fn plan_from_sequences(&self, sequences: &[Vec<(u32, usize)>], time_limit: u32) -> ValvePlan {
+        let openings = sequences
             .iter()
-            .filter_map(|(valve, flow_rate)| {
-                if *flow_rate > 0 {
-                    Some(valve.clone())
-                } else {
-                    None
-                }
+            .map(|sequence| {
+                let mut previous_valve = self.start;
+
+                sequence
+                    .iter()
+                    .map(|&(time, valve)| {
+                        let opening = ValveOpening {
+                            valve: self.valve_names[valve].clone(),
+                            route: self.route(previous_valve, valve),
+                            opened_at: time,
+                            flow_rate: self.flow_rates[valve],
+                        };
+
+                        previous_valve = valve;
+                        opening
+                    })
+                    .collect()
             })
             .collect();
 
-        let mut exploration_stack = vec![ExplorationAction::Backtrack];
+        ValvePlan {
+            time_limit,
+            openings,
+        }
+    }
+
+    // Returns the valves visited on the way from `start` to `end`, including `end`, but not
+    // including `start`
+    fn route(&self, start: usize, end: usize) -> Vec<String> {
+        let mut route = vec![];
+        let mut valve = start;
+
+        while valve != end {
+            valve = *self.connections[valve]
+                .iter()
+                .find(|&&neighbor| {
+                    self.travel_times[neighbor][end].saturating_add(1)
+                        == self.travel_times[valve][end]
+                })
+                .expect("End valve should be reachable from start valve");
+
+            route.push(self.valve_names[valve].clone());
+        }
 
-        unopened_valves
+        route
+    }
+
+    #[cfg(test)]
+    fn valve_index(&self, name: &str) -> Option<usize> {
+        self.valve_names.iter().position(|valve| valve == name)
+    }
+
+    // Returns the tunnel network as a Graphviz graph; if every tunnel goes both ways, the graph is
+    // undirected
+    fn to_dot(&self) -> String {
+        let undirected = self
+            .connections
             .iter()
-            .filter(|valve| self.can_open_valve(START, valve, 0, time_limit))
-            .for_each(|unopened_valve| {
-                for actor in 0..actors {
-                    exploration_stack
-                        .push(ExplorationAction::Explore(actor, unopened_valve.clone()));
-                }
+            .enumerate()
+            .all(|(valve, neighbors)| {
+                neighbors
+                    .iter()
+                    .all(|&neighbor| self.connections[neighbor].contains(&valve))
             });
 
-        let mut path: Vec<(u32, u32, String)> = vec![];
-        let mut max_pressure_released = 0;
-
-        while !exploration_stack.is_empty() {
-            match exploration_stack.pop().unwrap() {
-                ExplorationAction::Explore(actor, valve) => {
-                    let mut actor_states: Vec<(u32, u32, String)> = (0..actors)
-                        .map(|actor| {
-                            path.iter()
-                                .filter(|(a, _, _)| a == &actor)
-                                .last()
-                                .map(|(a, time, valve)| (*a, *time, valve.clone()))
-                                .unwrap_or_else(|| (actor, 0, START.to_string()))
-                        })
-                        .collect();
-
-                    let (_, previous_time, previous_valve) = &actor_states[actor as usize];
-                    let travel_time = self.travel_cost(previous_valve, &valve);
-                    let current_time = previous_time + travel_time + 1;
-
-                    path.push((actor, current_time, valve.clone()));
-                    unopened_valves.remove(valve.as_str());
-
-                    actor_states[actor as usize] = (actor, current_time, valve);
-
-                    // Whichever actor is the farthest "back in time" should move next to catch up
-                    // to the present
-                    let actor_queue: Vec<usize> = {
-                        let mut last_action_times = vec![0; actors as usize];
-
-                        actor_states.iter().for_each(|(actor, time, _)| {
-                            last_action_times[*actor as usize] = *time;
-                        });
-
-                        last_action_times
-                            .iter()
-                            .enumerate()
-                            .sorted_by_key(|(_, time)| *time)
-                            .map(|(actor, _)| actor)
-                            .collect()
-                    };
-
-                    let mut next_actions = vec![];
-
-                    for actor in actor_queue {
-                        next_actions = {
-                            let (_, time, valve) = &actor_states[actor];
-
-                            unopened_valves
-                                .iter()
-                                .filter(|candidate| {
-                                    self.can_open_valve(&valve, candidate, *time, time_limit)
-                                })
-                                .map(|candidate| {
-                                    ExplorationAction::Explore(actor as u32, candidate.clone())
-                                })
-                                .collect()
-                        };
+        let (graph_type, edge_operator) = if undirected {
+            ("graph", "--")
+        } else {
+            ("digraph", "->")
+        };
 
-                        // The laggiest actor may not have any valid moves; a more recent actor may
-                        // still be able to move, so continue looping if we don't find any actions.
-                        if !next_actions.is_empty() {
-                            break;
-                        }
-                    }
+        let mut dot = format!("{} volcano {{\n", graph_type);
 
-                    exploration_stack.push(ExplorationAction::Backtrack);
+        for (valve, name) in self.valve_names.iter().enumerate() {
+            let shape = if valve == self.start {
+                "doublecircle"
+            } else if self.flow_rates[valve] > 0 {
+                "box"
+            } else {
+                "circle"
+            };
 
-                    if next_actions.is_empty() {
-                        // We've reached the end of the line
-                        max_pressure_released = max_pressure_released
-                            .max(self.pressure_released(path.as_slice(), time_limit));
-                    } else {
-                        exploration_stack.append(&mut next_actions);
-                    }
-                }
-                ExplorationAction::Backtrack => {
-                    path.pop()
-                        .map(|(_, _, popped_valve)| unopened_valves.insert(popped_valve));
+            dot.push_str(&format!(
+                "    {} [label=\"{}\\nflow rate={}\", shape={}];\n",
+                name, name, self.flow_rates[valve], shape
+            ));
+        }
+
+        for (valve, neighbors) in self.connections.iter().enumerate() {
+            for &neighbor in neighbors {
+                if !undirected || valve < neighbor {
+                    dot.push_str(&format!(
+                        "    {} {} {};\n",
+                        self.valve_names[valve], edge_operator, self.valve_names[neighbor]
+                    ));
                 }
             }
         }
 
-        max_pressure_released
+        dot.push_str("}\n");
+        dot
     }
+}
 
-    fn can_open_valve(
+// The parts of a single actor's search that stay the same from one opening to the next
+struct Search<'a, V> {
+    travel_times: &'a [Vec<u32>],
+    flow_rates: &'a [u32],
+    useful_valves: &'a [usize],
+    time_limit: u32,
+    allowed_valves: usize,
+    visit: V,
+}
+
+impl<V: FnMut(usize, u32, &[(u32, usize)])> Search<'_, V> {
+    // Visits every sequence of `(time, valve)` openings a single actor could perform in the time
+    // limit using only the allowed valves
+    fn explore(
         &mut self,
-        start: &str,
-        valve: &str,
-        current_time: u32,
-        time_limit: u32,
-    ) -> bool {
-        current_time + self.travel_cost(start, valve) <= time_limit - 2
-    }
+        valve: usize,
+        time: u32,
+        opened_valves: usize,
+        pressure: u32,
+        sequence: &mut Vec<(u32, usize)>,
+    ) {
+        (self.visit)(opened_valves, pressure, sequence);
+
+        for (bit, &next_valve) in self.useful_valves.iter().enumerate() {
+            if self.allowed_valves & !opened_valves & (1 << bit) == 0 {
+                continue;
+            }
 
-    fn travel_cost(&mut self, start: &str, end: &str) -> u32 {
-        if start == end {
-            0
-        } else {
-            *self
-                .travel_times
-                .get(&(start.to_string(), end.to_string()))
-                .unwrap()
+            let opened_at = time
+                .saturating_add(self.travel_times[valve][next_valve])
+                .saturating_add(1);
+
+            if opened_at < self.time_limit {
+                sequence.push((opened_at, next_valve));
+
+                self.explore(
+                    next_valve,
+                    opened_at,
+                    opened_valves | (1 << bit),
+                    pressure + self.flow_rates[next_valve] * (self.time_limit - opened_at),
+                    sequence,
+                );
+
+                sequence.pop();
+            }
         }
     }
+}
 
-    fn pressure_released(&self, sequence: &[(u32, u32, String)], time_limit: u32) -> u32 {
-        sequence
+#[derive(Debug, Eq, PartialEq)]
+struct ValveOpening {
+    valve: String,
+    route: Vec<String>,
+    opened_at: u32,
+    flow_rate: u32,
+}
+
+impl ValveOpening {
+    fn departed_at(&self) -> u32 {
+        self.opened_at - self.route.len() as u32 - 1
+    }
+}
+
+#[derive(Debug)]
+struct ValvePlan {
+    time_limit: u32,
+    openings: Vec<Vec<ValveOpening>>,
+}
+
+impl ValvePlan {
+    fn pressure_released(&self) -> u32 {
+        self.openings
             .iter()
-            .map(|(_, time, valve)| {
-                self.flow_rates.get(valve).expect("Valve should be present") * (time_limit - time)
-            })
+            .flatten()
+            .map(|opening| opening.flow_rate * (self.time_limit - opening.opened_at))
             .sum()
     }
+
+    fn actor_name(actor: usize) -> String {
+        match actor {
+            0 => String::from("You"),
+            1 => String::from("The elephant"),
+            _ => format!("Elephant {}", actor),
+        }
+    }
 }
 
-#[derive(Debug)]
-enum ExplorationAction {
-    Explore(u32, String),
-    Backtrack,
+impl Display for ValvePlan {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mut cumulative_pressure = 0;
+
+        for minute in 1..=self.time_limit {
+            writeln!(f, "== Minute {} ==", minute)?;
+
+            let open_valves: Vec<&ValveOpening> = self
+                .openings
+                .iter()
+                .flatten()
+                .filter(|opening| opening.opened_at < minute)
+                .sorted_by(|a, b| a.valve.cmp(&b.valve))
+                .collect();
+
+            let pressure: u32 = open_valves.iter().map(|opening| opening.flow_rate).sum();
+            cumulative_pressure += pressure;
+
+            match open_valves.as_slice() {
+                [] => writeln!(f, "No valves are open.")?,
+                [opening] => writeln!(
+                    f,
+                    "Valve {} is open, releasing {} pressure ({} total).",
+                    opening.valve, pressure, cumulative_pressure
+                )?,
+                [openings @ .., last] => writeln!(
+                    f,
+                    "Valves {}{} and {} are open, releasing {} pressure ({} total).",
+                    openings.iter().map(|opening| &opening.valve).join(", "),
+                    if openings.len() > 1 { "," } else { "" },
+                    last.valve,
+                    pressure,
+                    cumulative_pressure
+                )?,
+            }
+
+            for (actor, openings) in self.openings.iter().enumerate() {
+                if let Some(opening) = openings
+                    .iter()
+                    .find(|opening| opening.departed_at() < minute && minute <= opening.opened_at)
+                {
+                    let name = ValvePlan::actor_name(actor);
+
+                    if minute == opening.opened_at {
+                        let verb = if actor == 0 { "open" } else { "opens" };
+                        writeln!(f, "{} {} valve {}.", name, verb, opening.valve)?;
+                    } else {
+                        let verb = if actor == 0 { "move" } else { "moves" };
+                        let destination =
+                            &opening.route[(minute - opening.departed_at()) as usize - 1];
+                        writeln!(f, "{} {} to valve {}.", name, verb, destination)?;
+                    }
+                }
+            }
+
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -300,40 +583,233 @@ mod test {
     "};
 
     #[test]
-    fn test_travel_cost() {
+    fn test_travel_times() {
+        let volcano = Volcano::from_str(TEST_VALVES).unwrap();
+
+        let aa = volcano.valve_index("AA").unwrap();
+        let bb = volcano.valve_index("BB").unwrap();
+        let cc = volcano.valve_index("CC").unwrap();
+
+        let hh = volcano.valve_index("HH").unwrap();
+        let jj = volcano.valve_index("JJ").unwrap();
+
+        assert_eq!(0, volcano.travel_times[aa][aa]);
+        assert_eq!(1, volcano.travel_times[aa][bb]);
+        assert_eq!(2, volcano.travel_times[aa][cc]);
+        assert_eq!(7, volcano.travel_times[hh][jj]);
+        assert_eq!(7, volcano.travel_times[jj][hh]);
+    }
+
+    #[test]
+    fn test_with_start_valve() {
+        let mut volcano = Volcano::with_start_valve(TEST_VALVES, "HH").unwrap();
+        assert_eq!(1807, volcano.optimal_plan(1, 30).pressure_released());
+
+        assert!(Volcano::with_start_valve(TEST_VALVES, "ZZ").is_err());
+    }
+
+    #[test]
+    fn test_unreachable_valve() {
+        let disconnected_valves = indoc! {"
+            Valve AA has flow rate=0; tunnel leads to valve BB
+            Valve BB has flow rate=13; tunnel leads to valve AA
+            Valve CC has flow rate=2; tunnel leads to valve DD
+            Valve DD has flow rate=0; tunnel leads to valve CC
+        "};
+
+        assert!(Volcano::from_str(disconnected_valves).is_err());
+        assert!(Volcano::with_start_valve(disconnected_valves, "CC").is_err());
+
+        // Unreachable valves are fine as long as they're not worth visiting
+        assert!(Volcano::from_str(indoc! {"
+            Valve AA has flow rate=0; tunnel leads to valve BB
+            Valve BB has flow rate=13; tunnel leads to valve AA
+            Valve CC has flow rate=0; tunnel leads to valve DD
+            Valve DD has flow rate=0; tunnel leads to valve CC
+        "})
+        .is_ok());
+    }
+
+    #[test]
+    fn test_unknown_valve() {
+        assert!(Volcano::from_str(indoc! {"
+            Valve AA has flow rate=0; tunnel leads to valve BB
+            Valve BB has flow rate=13; tunnels lead to valves AA, CC
+        "})
+        .is_err());
+    }
+
+    #[test]
+    fn test_to_dot() {
+        let volcano = Volcano::from_str(indoc! {"
+            Valve AA has flow rate=0; tunnels lead to valves BB, CC
+            Valve BB has flow rate=13; tunnel leads to valve AA
+            Valve CC has flow rate=2; tunnel leads to valve AA
+        "})
+        .unwrap();
+
+        assert_eq!(
+            indoc! {r#"
+                graph volcano {
+                    AA [label="AA\nflow rate=0", shape=doublecircle];
+                    BB [label="BB\nflow rate=13", shape=box];
+                    CC [label="CC\nflow rate=2", shape=box];
+                    AA -- BB;
+                    AA -- CC;
+                }
+            "#},
+            volcano.to_dot()
+        );
+
+        let one_way_volcano = Volcano::from_str(indoc! {"
+            Valve AA has flow rate=0; tunnel leads to valve BB
+            Valve BB has flow rate=13; tunnel leads to valve CC
+            Valve CC has flow rate=0; tunnel leads to valve AA
+        "})
+        .unwrap();
+
+        assert_eq!(
+            indoc! {r#"
+                digraph volcano {
+                    AA [label="AA\nflow rate=0", shape=doublecircle];
+                    BB [label="BB\nflow rate=13", shape=box];
+                    CC [label="CC\nflow rate=0", shape=circle];
+                    AA -> BB;
+                    BB -> CC;
+                    CC -> AA;
+                }
+            "#},
+            one_way_volcano.to_dot()
+        );
+    }
+
+    #[test]
+    fn test_optimal_plan_pressure_released() {
         let mut volcano = Volcano::from_str(TEST_VALVES).unwrap();
 
-        assert_eq!(0, volcano.travel_cost("AA", "AA"));
-        assert_eq!(1, volcano.travel_cost("AA", "BB"));
-        assert_eq!(2, volcano.travel_cost("AA", "CC"));
+        assert_eq!(1651, volcano.optimal_plan(1, 30).pressure_released());
+        assert_eq!(1707, volcano.optimal_plan(2, 26).pressure_released());
+        assert_eq!(1794, volcano.optimal_plan(3, 26).pressure_released());
+        assert_eq!(1339, volcano.optimal_plan(4, 20).pressure_released());
     }
 
     #[test]
-    fn test_maximum_pressure_release() {
+    fn test_optimal_plan_disjoint_valves() {
         let mut volcano = Volcano::from_str(TEST_VALVES).unwrap();
+        let plan = volcano.optimal_plan(3, 20);
+
+        let opened_valves: Vec<&str> = plan
+            .openings
+            .iter()
+            .flatten()
+            .map(|opening| opening.valve.as_str())
+            .collect();
 
-        assert_eq!(1651, volcano.maximum_pressure_release(1, 30));
-        assert_eq!(1707, volcano.maximum_pressure_release(2, 26));
+        assert_eq!(3, plan.openings.len());
+        assert_eq!(1308, plan.pressure_released());
+        assert_eq!(opened_valves.len(), opened_valves.iter().unique().count());
+    }
+
+    #[test]
+    fn test_optimal_plan() {
+        let mut volcano = Volcano::from_str(TEST_VALVES).unwrap();
+        let plan = volcano.optimal_plan(1, 30);
+
+        assert_eq!(
+            vec![
+                ("DD", 2),
+                ("BB", 5),
+                ("JJ", 9),
+                ("HH", 17),
+                ("EE", 21),
+                ("CC", 24)
+            ],
+            plan.openings[0]
+                .iter()
+                .map(|opening| (opening.valve.as_str(), opening.opened_at))
+                .collect::<Vec<(&str, u32)>>()
+        );
+
+        assert_eq!(vec!["AA", "II", "JJ"], plan.openings[0][2].route);
+    }
+
+    #[test]
+    #[should_panic(expected = "Plans need at least one actor")]
+    fn test_optimal_plan_no_actors() {
+        Volcano::from_str(TEST_VALVES).unwrap().optimal_plan(0, 30);
+    }
+
+    #[test]
+    fn test_optimal_plan_one_way_dead_end() {
+        // Nothing can be reached from XX, so routes from AA must not try to go through it
+        let mut volcano = Volcano::from_str(indoc! {"
+            Valve AA has flow rate=0; tunnels lead to valves XX, BB
+            Valve BB has flow rate=13; tunnel leads to valve AA
+            Valve XX has flow rate=0; tunnel leads to valve XX
+        "})
+        .unwrap();
+
+        let plan = volcano.optimal_plan(1, 30);
+
+        assert_eq!(1, plan.openings[0].len());
+        assert_eq!("BB", plan.openings[0][0].valve);
+        assert_eq!(2, plan.openings[0][0].opened_at);
+        assert_eq!(vec!["BB"], plan.openings[0][0].route);
+    }
+
+    #[test]
+    fn test_plan_display() {
+        let mut volcano = Volcano::from_str(TEST_VALVES).unwrap();
+        let narrative = volcano.optimal_plan(1, 30).to_string();
+
+        assert!(narrative.starts_with(indoc! {"
+            == Minute 1 ==
+            No valves are open.
+            You move to valve DD.
+
+            == Minute 2 ==
+            No valves are open.
+            You open valve DD.
+
+            == Minute 3 ==
+            Valve DD is open, releasing 20 pressure (20 total).
+            You move to valve CC.
+        "}));
+
+        assert!(narrative.contains(indoc! {"
+            == Minute 21 ==
+            Valves BB, DD, HH, and JJ are open, releasing 76 pressure (928 total).
+            You open valve EE.
+        "}));
+
+        assert!(narrative.ends_with(indoc! {"
+            == Minute 30 ==
+            Valves BB, CC, DD, EE, HH, and JJ are open, releasing 81 pressure (1651 total).
+
+        "}));
     }
 
     #[test]
     fn test_pressure_released() {
         let volcano = Volcano::from_str(TEST_VALVES).unwrap();
 
+        let sequence = [
+            (2, "DD"),
+            (5, "BB"),
+            (9, "JJ"),
+            (17, "HH"),
+            (21, "EE"),
+            (24, "CC"),
+        ]
+        .iter()
+        .map(|(time, valve)| (*time, volcano.valve_index(valve).unwrap()))
+        .collect();
+
         assert_eq!(
             1651,
-            volcano.pressure_released(
-                vec![
-                    (0, 2, "DD".to_string()),
-                    (0, 5, "BB".to_string()),
-                    (0, 9, "JJ".to_string()),
-                    (0, 17, "HH".to_string()),
-                    (0, 21, "EE".to_string()),
-                    (0, 24, "CC".to_string()),
-                ]
-                .as_slice(),
-                30
-            )
+            volcano
+                .plan_from_sequences(&[sequence], 30)
+                .pressure_released()
         );
     }
 }
@@ -1,272 +1,347 @@
+use std::collections::HashMap;
 use std::error::Error;
 use std::fmt::{Display, Formatter};
 use std::fs;
 use std::str::FromStr;
 
-const CAVE_WIDTH: usize = 7;
+const DEFAULT_ROCKS: &str = "\
+####
+
+.#.
+###
+.#.
+
+..#
+..#
+###
+
+#
+#
+#
+#
+
+##
+##";
 
 fn main() -> Result<(), Box<dyn Error>> {
     let args: Vec<String> = std::env::args().collect();
 
     if let Some(path) = args.get(1) {
+        let config = if let Some(rocks_path) = args.get(2) {
+            CaveConfig {
+                rocks: Rock::parse_sequence(fs::read_to_string(rocks_path)?.as_str())?,
+                ..Default::default()
+            }
+        } else {
+            CaveConfig::default()
+        };
+
         for rocks in [2022, 1_000_000_000_000u64] {
-            let cave = Cave::from_str(fs::read_to_string(path)?.as_str())?;
+            let cave = Cave::new(fs::read_to_string(path)?.as_str(), config.clone())?;
+            let (tower_height, cycle) = cave.tower_height(rocks);
 
             println!(
                 "Tower height after adding {} rocks: {}",
-                rocks,
-                cave.tower_height(rocks)
+                rocks, tower_height
             );
+
+            if let Some(cycle) = cycle {
+                println!(
+                    "  Extrapolated from a cycle of {} rocks starting after rock {}, adding {} rows per cycle",
+                    cycle.cycle_length, cycle.cycle_start, cycle.height_per_cycle
+                );
+            }
         }
 
         Ok(())
     } else {
-        Err("Usage: day17 INPUT_FILE_PATH".into())
+        Err("Usage: day17 INPUT_FILE_PATH [ROCKS_FILE_PATH]".into())
     }
 }
 
 struct Cave {
-    spaces: Vec<Space>,
+    // One bitmask per row, starting from the bottom of the retained part of the cave; bit `x` is
+    // set if the space `x` spaces from the left wall is filled with rock
+    rows: Vec<u8>,
     rocks_added: usize,
+    tower_height: usize,
+
+    // Rows below the lowest row any falling rock could reach get discarded; this is the number of
+    // rows discarded so far
+    pruned_rows: usize,
+
+    width: usize,
+    spawn_offset: (usize, usize),
 
-    rocks: [Rock; 5],
+    rocks: Vec<Rock>,
     next_rock: usize,
 
     jet_pattern: Vec<Jet>,
     next_jet: usize,
 }
 
-impl FromStr for Cave {
-    type Err = Box<dyn Error>;
+#[derive(Clone)]
+struct CaveConfig {
+    width: usize,
 
-    fn from_str(string: &str) -> Result<Self, Self::Err> {
-        let jet_pattern = string
-            .chars()
-            .map(|c| match c {
-                '<' => Ok(Jet::Left),
-                '>' => Ok(Jet::Right),
-                _ => Err("Unexpected character".into()),
-            })
-            .collect::<Result<_, Box<dyn Error>>>()?;
+    // The horizontal offset from the left wall and the vertical offset from the top of the tower
+    // at which new rocks appear
+    spawn_offset: (usize, usize),
 
-        Ok(Cave {
-            spaces: vec![],
-            rocks_added: 0,
+    rocks: Vec<Rock>,
+}
 
-            rocks: [
-                Rock::h_bar(),
-                Rock::cross(),
-                Rock::corner(),
-                Rock::v_bar(),
-                Rock::square(),
-            ],
-            next_rock: 0,
+impl Default for CaveConfig {
+    fn default() -> Self {
+        CaveConfig {
+            width: 7,
+            spawn_offset: (2, 3),
+            rocks: Rock::parse_sequence(DEFAULT_ROCKS).expect("Default rocks should be valid"),
+        }
+    }
+}
 
-            jet_pattern,
-            next_jet: 0,
-        })
+impl FromStr for Cave {
+    type Err = Box<dyn Error>;
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        Cave::new(string, CaveConfig::default())
     }
 }
 
 impl Display for Cave {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        self.spaces
-            .chunks_exact(CAVE_WIDTH)
-            .rev()
-            .try_for_each(|row| {
-                let row_string: String = row
-                    .iter()
-                    .map(|space| match space {
-                        Space::Empty => '.',
-                        Space::Rock => '#',
-                    })
-                    .collect();
-
-                writeln!(f, "{}", row_string)
-            })?;
+        self.rows.iter().rev().try_for_each(|row| {
+            let row_string: String = (0..self.width)
+                .map(|x| if row & (1 << x) != 0 { '#' } else { '.' })
+                .collect();
+
+            writeln!(f, "{}", row_string)
+        })?;
 
         Ok(())
     }
 }
 
 impl Cave {
-    fn tower_height(mut self, rocks: u64) -> u64 {
-        // Hypothesis: as we add rocks, we'll wind up with an irregular tower "base," but then
-        // eventually settle into a repeating pattern. Here, we add rocks until we find a repeating
-        // pattern, then figure out the base tower height, then figure out how long the repeating
-        // cycle is.
-
-        // We can't possibly have a cycle until (a) we've added at least one of each rock, and then
-        // that cycle can't possibly be shorter than the height of the tallest individual rock.
-        const MIN_CYCLE_HEIGHT: usize = 4;
+    fn new(jet_pattern: &str, config: CaveConfig) -> Result<Self, Box<dyn Error>> {
+        if config.width == 0 || config.width > u8::BITS as usize {
+            return Err(format!("Cave width must be between 1 and {}", u8::BITS).into());
+        }
 
-        let (cycle_start, cycle_end) = 'find_cycle: loop {
-            self.add_rock();
+        if config.rocks.is_empty() {
+            return Err("Must have at least one rock".into());
+        }
 
-            let tower_height = self.current_tower_height();
+        if config
+            .rocks
+            .iter()
+            .any(|rock| config.spawn_offset.0 + rock.width() > config.width)
+        {
+            return Err("All rocks must fit in the cave at their spawn position".into());
+        }
 
-            if self.rocks_added as u64 == rocks {
-                return tower_height as u64;
-            }
+        let jet_pattern: Vec<Jet> = jet_pattern
+            .trim_end()
+            .chars()
+            .map(|c| match c {
+                '<' => Ok(Jet::Left),
+                '>' => Ok(Jet::Right),
+                _ => Err("Unexpected character".into()),
+            })
+            .collect::<Result<_, Box<dyn Error>>>()?;
 
-            if self.rocks_added as usize > self.rocks.len() && tower_height >= 2 * MIN_CYCLE_HEIGHT
-            {
-                for potential_cycle_height in MIN_CYCLE_HEIGHT..=tower_height / 2 {
-                    let top = tower_height * CAVE_WIDTH;
-                    let midpoint = top - (potential_cycle_height * CAVE_WIDTH);
-                    let bottom = top - (2 * potential_cycle_height * CAVE_WIDTH);
-
-                    if self.spaces[midpoint..top] == self.spaces[bottom..midpoint] {
-                        // We've found a cycle! We know how tall it is, but not how many rocks went
-                        // into it.
-                        break 'find_cycle (midpoint, top);
-                    }
-                }
-            }
-        };
+        if jet_pattern.is_empty() {
+            return Err("Jet pattern must not be empty".into());
+        }
 
-        // Now we know how tall the cycle is; let's figure out how many rocks go into that cycle.
-        let rocks_added_before_repeat = self.rocks_added;
+        Ok(Cave {
+            rows: vec![],
+            rocks_added: 0,
+            tower_height: 0,
+            pruned_rows: 0,
 
-        loop {
-            // The cycle can only reasonably repeat after adding a full round of rocks
-            for _ in 0..self.rocks.len() {
-                self.add_rock();
+            width: config.width,
+            spawn_offset: config.spawn_offset,
 
-                if self.rocks_added as u64 == rocks {
-                    return self.current_tower_height() as u64;
-                }
-            }
+            rocks: config.rocks,
+            next_rock: 0,
 
-            let end = self.current_tower_height() * CAVE_WIDTH;
-            let start = end - (cycle_end - cycle_start);
+            jet_pattern,
+            next_jet: 0,
+        })
+    }
 
-            if self.spaces[start..end] == self.spaces[cycle_start..cycle_end] {
-                // The cycle has repeated!
-                break;
-            }
-        }
+    // Returns the height of the tower after adding the given number of rocks; if we found a cycle
+    // along the way and used it to extrapolate the height, returns that cycle, too.
+    fn tower_height(mut self, rocks: u64) -> (u64, Option<TowerCycle>) {
+        // Everything that happens next depends only on which rock and jet come next and on the
+        // shape of the reachable part of the tower. If we see the same combination twice, we've
+        // found a cycle.
+        let mut seen_states: HashMap<(usize, usize, Vec<u8>), u64> = HashMap::new();
+        let mut heights = vec![0];
 
-        let rocks_per_cycle = self.rocks_added - rocks_added_before_repeat;
+        while (self.rocks_added as u64) < rocks {
+            self.add_rock();
+            heights.push(self.current_tower_height() as u64);
 
-        // We now know enough to calculate the number of rocks per cycle, and we know we've got
-        // three full cycles plus the initial bottom tower. That tells us how many rocks are in the
-        // base tower and how tall the base tower is.
-        let rocks_in_base_tower = self.rocks_added - (3 * rocks_per_cycle);
-        let base_tower_height =
-            self.current_tower_height() - (3 * (cycle_end - cycle_start) / CAVE_WIDTH);
+            let state = (self.next_rock, self.next_jet, self.surface_profile());
 
-        // This is MOST of what we need to calculate the full height of the tower. There may be a
-        // few stragglers after the last cycle boundary; we'll just add those and see how the height
-        // changes.
-        let rocks_after_last_cycle = (rocks as usize - rocks_in_base_tower) % rocks_per_cycle;
+            if let Some(&cycle_start) = seen_states.get(&state) {
+                let cycle_end = self.rocks_added as u64;
 
-        let height_from_rocks_after_last_cycle = {
-            let initial_height = self.current_tower_height();
+                let cycle = TowerCycle {
+                    cycle_start,
+                    cycle_length: cycle_end - cycle_start,
+                    height_per_cycle: heights[cycle_end as usize] - heights[cycle_start as usize],
+                    heights,
+                };
 
-            for _ in 0..rocks_after_last_cycle {
-                self.add_rock();
+                return (cycle.tower_height(rocks), Some(cycle));
             }
 
-            self.current_tower_height() - initial_height
-        };
+            seen_states.insert(state, self.rocks_added as u64);
+        }
 
-        // And, finally, putting it all together…
-        let cycle_height = (cycle_end - cycle_start) / CAVE_WIDTH;
-        let full_cycles = (rocks as usize - rocks_in_base_tower) / rocks_per_cycle;
+        (self.current_tower_height() as u64, None)
+    }
 
-        (base_tower_height + (full_cycles * cycle_height) + height_from_rocks_after_last_cycle)
-            as u64
+    // Returns the filled/empty state of every retained row up to the top of the tower
+    fn surface_profile(&self) -> Vec<u8> {
+        self.rows[..self.tower_height - self.pruned_rows].to_vec()
     }
 
     fn add_rock(&mut self) {
-        let mut position = (2, self.current_tower_height() + 3);
-
-        let rock = self.rocks[self.next_rock].clone();
+        let rock = self.next_rock;
         self.next_rock = (self.next_rock + 1) % self.rocks.len();
 
+        let rock_width = self.rocks[rock].width();
+        let rock_height = self.rocks[rock].height();
+
+        let mut x = self.spawn_offset.0;
+        let mut y = self.tower_height - self.pruned_rows + self.spawn_offset.1;
+
         // Do we need to add rows to the cave?
-        if self.cave_height() < position.1 + rock.height() {
-            let additional_rows = position.1 + rock.height() - self.cave_height();
-            self.spaces
-                .append(&mut vec![Space::Empty; additional_rows * CAVE_WIDTH]);
+        if self.rows.len() < y + rock_height {
+            self.rows.resize(y + rock_height, 0);
         }
 
         loop {
             let jet = &self.jet_pattern[self.next_jet];
             self.next_jet = (self.next_jet + 1) % self.jet_pattern.len();
 
-            let blocked_by_cave_wall = match jet {
-                Jet::Left => position.0 == 0,
-                Jet::Right => position.0 + rock.width() == CAVE_WIDTH,
-            };
-
-            position = if !blocked_by_cave_wall {
-                let gusted_position = match jet {
-                    Jet::Left => (position.0 - 1, position.1),
-                    Jet::Right => (position.0 + 1, position.1),
-                };
-
-                if self.collides_with_rock(&rock, gusted_position) {
-                    position
-                } else {
-                    gusted_position
+            match jet {
+                Jet::Left => {
+                    if x > 0 && !self.collides_with_rock(rock, x - 1, y) {
+                        x -= 1;
+                    }
                 }
-            } else {
-                position
-            };
+                Jet::Right => {
+                    if x + rock_width < self.width && !self.collides_with_rock(rock, x + 1, y) {
+                        x += 1;
+                    }
+                }
+            }
 
-            if position.1 == 0 {
-                // We've reached the bottom
-                self.fill_with_rock(&rock, position);
+            if y == 0 || self.collides_with_rock(rock, x, y - 1) {
+                // Falling would cause a collision (or we've reached the bottom), so settle the rock
+                // where it is
+                self.fill_with_rock(rock, x, y);
                 break;
-            } else {
-                let position_after_fall = (position.0, position.1 - 1);
-
-                if self.collides_with_rock(&rock, position_after_fall) {
-                    // Falling would cause a collision, so settle the rock where it is
-                    self.fill_with_rock(&rock, position);
-                    break;
-                } else {
-                    position = position_after_fall;
-                }
             }
+
+            y -= 1;
         }
 
+        self.tower_height = self.tower_height.max(self.pruned_rows + y + rock_height);
+
         self.rocks_added += 1;
+        self.prune();
     }
 
-    fn cave_height(&self) -> usize {
-        self.spaces.len() / CAVE_WIDTH
+    // Discards rows that no falling rock could ever reach again
+    fn prune(&mut self) {
+        let retained_height = self.tower_height - self.pruned_rows;
+        let all_spaces = u8::MAX >> (u8::BITS as usize - self.width);
+
+        // Working down from the empty row just above the top of the tower, find every space that
+        // could be reached by moving left, right, or down through empty spaces. A rock is never
+        // smaller than a single space, so no rock could ever reach any space that a single space
+        // couldn't.
+        let mut reachable_spaces = all_spaces;
+        let mut lowest_reachable_row = retained_height;
+
+        for y in (0..retained_height).rev() {
+            let empty_spaces = !self.rows[y] & all_spaces;
+            let mut reachable_spaces_in_row = reachable_spaces & empty_spaces;
+
+            loop {
+                let spread = (reachable_spaces_in_row
+                    | reachable_spaces_in_row << 1
+                    | reachable_spaces_in_row >> 1)
+                    & empty_spaces;
+
+                if spread == reachable_spaces_in_row {
+                    break;
+                }
+
+                reachable_spaces_in_row = spread;
+            }
+
+            if reachable_spaces_in_row == 0 {
+                break;
+            }
+
+            reachable_spaces = reachable_spaces_in_row;
+            lowest_reachable_row = y;
+        }
+
+        if lowest_reachable_row > 0 {
+            self.rows.drain(..lowest_reachable_row);
+            self.pruned_rows += lowest_reachable_row;
+        }
     }
 
     fn current_tower_height(&self) -> usize {
-        if self.spaces.is_empty() {
-            0
-        } else {
-            self.spaces
-                .chunks_exact(CAVE_WIDTH)
-                .enumerate()
-                .rev()
-                .find(|(_, row)| row.iter().any(|space| matches!(space, Space::Rock)))
-                .map(|(i, _)| i)
-                .expect("Non-empty rows should have at least one rock space")
-                + 1
-        }
+        self.tower_height
     }
 
-    fn collides_with_rock(&self, rock: &Rock, position: (usize, usize)) -> bool {
-        rock.filled_spaces
+    fn collides_with_rock(&self, rock: usize, x: usize, y: usize) -> bool {
+        self.rocks[rock]
+            .rows
             .iter()
-            .map(|(x, y)| (x + position.0, y + position.1))
-            .any(|(x, y)| matches!(self.spaces[(y * CAVE_WIDTH) + x], Space::Rock))
+            .enumerate()
+            .any(|(i, rock_row)| self.rows[y + i] & (rock_row << x) != 0)
     }
 
-    fn fill_with_rock(&mut self, rock: &Rock, position: (usize, usize)) {
-        rock.filled_spaces
-            .iter()
-            .map(|(x, y)| (x + position.0, y + position.1))
-            .for_each(|(x, y)| self.spaces[(y * CAVE_WIDTH) + x] = Space::Rock);
+    fn fill_with_rock(&mut self, rock: usize, x: usize, y: usize) {
+        for (i, rock_row) in self.rocks[rock].rows.iter().enumerate() {
+            self.rows[y + i] |= rock_row << x;
+        }
+    }
+}
+
+struct TowerCycle {
+    // The number of rocks added before the cycle begins
+    cycle_start: u64,
+    cycle_length: u64,
+    height_per_cycle: u64,
+
+    // The height of the tower after adding each number of rocks, up to the end of the first cycle
+    heights: Vec<u64>,
+}
+
+impl TowerCycle {
+    fn tower_height(&self, rocks: u64) -> u64 {
+        if rocks < self.heights.len() as u64 {
+            self.heights[rocks as usize]
+        } else {
+            let full_cycles = (rocks - self.cycle_start) / self.cycle_length;
+            let remaining_rocks = (rocks - self.cycle_start) % self.cycle_length;
+
+            self.heights[(self.cycle_start + remaining_rocks) as usize]
+                + (full_cycles * self.height_per_cycle)
+        }
     }
 }
 
@@ -276,54 +351,77 @@ enum Jet {
     Right,
 }
 
-#[derive(Copy, Clone, Eq, PartialEq)]
-enum Space {
-    Empty,
-    Rock,
-}
-
-#[derive(Clone)]
+#[derive(Clone, Debug, Eq, PartialEq)]
 struct Rock {
-    filled_spaces: Vec<(usize, usize)>,
+    // One bitmask per row, starting from the bottom of the rock; bit `x` is set if the space `x`
+    // spaces from the rock's left edge is filled
+    rows: Vec<u8>,
 }
 
-impl Rock {
-    fn h_bar() -> Rock {
-        Rock {
-            filled_spaces: vec![(0, 0), (1, 0), (2, 0), (3, 0)],
-        }
-    }
+impl FromStr for Rock {
+    type Err = Box<dyn Error>;
 
-    fn cross() -> Rock {
-        Rock {
-            filled_spaces: vec![(1, 0), (0, 1), (1, 1), (2, 1), (1, 2)],
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        // Rocks are drawn as they appear in the cave, so the last line of the drawing is the bottom
+        // of the rock
+        let mut filled_spaces = vec![];
+
+        for (y, line) in string.lines().rev().enumerate() {
+            for (x, c) in line.chars().enumerate() {
+                match c {
+                    '#' => filled_spaces.push((x, y)),
+                    '.' | ' ' => {}
+                    _ => return Err(format!("Unexpected character in rock: {}", c).into()),
+                }
+            }
         }
-    }
 
-    fn corner() -> Rock {
-        Rock {
-            filled_spaces: vec![(0, 0), (1, 0), (2, 0), (2, 1), (2, 2)],
+        if filled_spaces.is_empty() {
+            return Err("Rocks must have at least one filled space".into());
         }
-    }
 
-    fn v_bar() -> Rock {
-        Rock {
-            filled_spaces: vec![(0, 0), (0, 1), (0, 2), (0, 3)],
+        // Shift the rock so its lower-left corner is at (0, 0) even if the drawing has empty rows
+        // or columns
+        let min_x = filled_spaces.iter().map(|(x, _)| *x).min().unwrap();
+        let min_y = filled_spaces.iter().map(|(_, y)| *y).min().unwrap();
+        let max_y = filled_spaces.iter().map(|(_, y)| *y).max().unwrap();
+
+        let mut rows = vec![0u8; max_y - min_y + 1];
+
+        for (x, y) in filled_spaces {
+            if x - min_x >= u8::BITS as usize {
+                return Err(format!("Rocks must be at most {} spaces wide", u8::BITS).into());
+            }
+
+            rows[y - min_y] |= 1 << (x - min_x);
         }
+
+        Ok(Rock { rows })
     }
+}
 
-    fn square() -> Rock {
-        Rock {
-            filled_spaces: vec![(0, 0), (1, 0), (0, 1), (1, 1)],
-        }
+impl Rock {
+    // Parses a sequence of rocks separated by blank lines
+    fn parse_sequence(string: &str) -> Result<Vec<Rock>, Box<dyn Error>> {
+        string
+            .replace("\r\n", "\n")
+            .split("\n\n")
+            .filter(|drawing| !drawing.trim().is_empty())
+            .map(Rock::from_str)
+            .collect()
     }
 
     fn height(&self) -> usize {
-        *self.filled_spaces.iter().map(|(_, y)| y).max().unwrap() as usize + 1
+        self.rows.len()
     }
 
     fn width(&self) -> usize {
-        *self.filled_spaces.iter().map(|(x, _)| x).max().unwrap() as usize + 1
+        (u8::BITS
+            - self
+                .rows
+                .iter()
+                .fold(0, |all_rows, row| all_rows | row)
+                .leading_zeros()) as usize
     }
 }
 
@@ -337,6 +435,206 @@ mod test {
     fn test_tower_height() {
         let cave = Cave::from_str(TEST_PATTERN).unwrap();
 
-        assert_eq!(3068, cave.tower_height(2022));
+        assert_eq!(3068, cave.tower_height(2022).0);
+    }
+
+    #[test]
+    fn test_tower_height_with_cycle() {
+        let cave = Cave::from_str(TEST_PATTERN).unwrap();
+        let (tower_height, cycle) = cave.tower_height(1_000_000_000_000);
+
+        assert_eq!(1_514_285_714_288, tower_height);
+
+        let cycle = cycle.unwrap();
+        assert_eq!(35, cycle.cycle_length);
+        assert_eq!(53, cycle.height_per_cycle);
+
+        // Re-simulating should give the same result as extrapolating from the cycle
+        let mut cave = Cave::from_str(TEST_PATTERN).unwrap();
+
+        for rocks in 1..=(cycle.cycle_start + 3 * cycle.cycle_length) {
+            cave.add_rock();
+            assert_eq!(
+                cave.current_tower_height() as u64,
+                cycle.tower_height(rocks)
+            );
+        }
+    }
+
+    #[test]
+    fn test_tower_height_matches_long_simulation() {
+        const ROCKS: u64 = 1_000_000;
+
+        let mut cave = Cave::from_str(TEST_PATTERN).unwrap();
+        (0..ROCKS).for_each(|_| cave.add_rock());
+
+        assert_eq!(
+            cave.current_tower_height() as u64,
+            Cave::from_str(TEST_PATTERN).unwrap().tower_height(ROCKS).0
+        );
+    }
+
+    // A single-space rock in a single-space-wide cave can only ever stack straight up
+    fn single_column_config() -> CaveConfig {
+        CaveConfig {
+            width: 1,
+            spawn_offset: (0, 3),
+            rocks: Rock::parse_sequence("#").unwrap(),
+        }
+    }
+
+    // Same for rocks that span the entire width of the cave
+    fn full_width_config() -> CaveConfig {
+        CaveConfig {
+            width: 3,
+            spawn_offset: (0, 1),
+            rocks: Rock::parse_sequence("###\n\n###\n###").unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_tower_height_degenerate_cycle() {
+        let cave = Cave::new(TEST_PATTERN, single_column_config()).unwrap();
+
+        assert_eq!(1_000_000_000_000, cave.tower_height(1_000_000_000_000).0);
+
+        let cave = Cave::new(TEST_PATTERN, full_width_config()).unwrap();
+
+        assert_eq!(1_500_000_000_000, cave.tower_height(1_000_000_000_000).0);
+    }
+
+    #[test]
+    fn test_prune() {
+        let mut cave = Cave::from_str(TEST_PATTERN).unwrap();
+        (0..2022).for_each(|_| cave.add_rock());
+
+        assert_eq!(3068, cave.current_tower_height());
+        assert!(cave.pruned_rows > 3000);
+        assert!(cave.rows.len() < 100);
+    }
+
+    #[test]
+    fn test_rock_from_str() {
+        assert_eq!(
+            Rock {
+                rows: vec![0b010, 0b111, 0b010],
+            },
+            Rock::from_str(".#.\n###\n.#.").unwrap()
+        );
+
+        // Empty rows and columns should be trimmed
+        assert_eq!(
+            Rock { rows: vec![0b11] },
+            Rock::from_str("....\n.##.\n....").unwrap()
+        );
+
+        assert!(Rock::from_str("...").is_err());
+        assert!(Rock::from_str(".x.").is_err());
+        assert!(Rock::from_str("#########").is_err());
+    }
+
+    #[test]
+    fn test_parse_rock_sequence() {
+        let rocks = Rock::parse_sequence(DEFAULT_ROCKS).unwrap();
+
+        assert_eq!(
+            vec![(4, 1), (3, 3), (3, 3), (1, 4), (2, 2)],
+            rocks
+                .iter()
+                .map(|rock| (rock.width(), rock.height()))
+                .collect::<Vec<(usize, usize)>>()
+        );
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        assert!(Cave::new(
+            TEST_PATTERN,
+            CaveConfig {
+                width: 5,
+                ..Default::default()
+            }
+        )
+        .is_err());
+
+        assert!(Cave::new(
+            TEST_PATTERN,
+            CaveConfig {
+                width: 9,
+                ..Default::default()
+            }
+        )
+        .is_err());
+
+        assert!(Cave::new(
+            TEST_PATTERN,
+            CaveConfig {
+                rocks: vec![],
+                ..Default::default()
+            }
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_add_rock_custom_config() {
+        let mut cave = Cave::new(TEST_PATTERN, single_column_config()).unwrap();
+
+        (0..20).for_each(|_| cave.add_rock());
+        assert_eq!(20, cave.current_tower_height());
+
+        let mut cave = Cave::new(TEST_PATTERN, full_width_config()).unwrap();
+
+        (0..20).for_each(|_| cave.add_rock());
+        assert_eq!(30, cave.current_tower_height());
+
+        // Rocks should appear at the configured offset
+        let mut cave = Cave::new(
+            "<",
+            CaveConfig {
+                width: 5,
+                spawn_offset: (3, 0),
+                rocks: Rock::parse_sequence("#").unwrap(),
+            },
+        )
+        .unwrap();
+
+        cave.add_rock();
+        assert_eq!("..#..\n", cave.to_string());
+    }
+
+    #[test]
+    fn test_tower_height_matches_simulation() {
+        let configs = [
+            CaveConfig::default(),
+            CaveConfig {
+                width: 8,
+                spawn_offset: (3, 4),
+                ..Default::default()
+            },
+            CaveConfig {
+                width: 5,
+                spawn_offset: (1, 2),
+                rocks: Rock::parse_sequence("##\n\n#.\n##\n\n#\n#\n#").unwrap(),
+            },
+        ];
+
+        for config in configs {
+            for rocks in [1, 7, 100, 1000] {
+                let mut simulated_cave = Cave::new(TEST_PATTERN, config.clone()).unwrap();
+
+                for _ in 0..rocks {
+                    simulated_cave.add_rock();
+                }
+
+                assert_eq!(
+                    simulated_cave.current_tower_height() as u64,
+                    Cave::new(TEST_PATTERN, config.clone())
+                        .unwrap()
+                        .tower_height(rocks)
+                        .0
+                );
+            }
+        }
     }
 }
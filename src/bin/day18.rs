@@ -1,8 +1,19 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::error::Error;
 use std::fs;
 use std::str::FromStr;
 
+// The corners of each face of a unit cube, listed in the same order as `LavaDroplet::neighbors`;
+// corners are listed counter-clockwise as seen from outside the cube
+const FACE_CORNERS: [[(i32, i32, i32); 4]; 6] = [
+    [(1, 0, 0), (1, 1, 0), (1, 1, 1), (1, 0, 1)],
+    [(0, 0, 0), (0, 0, 1), (0, 1, 1), (0, 1, 0)],
+    [(0, 1, 0), (0, 1, 1), (1, 1, 1), (1, 1, 0)],
+    [(0, 0, 0), (1, 0, 0), (1, 0, 1), (0, 0, 1)],
+    [(0, 0, 1), (1, 0, 1), (1, 1, 1), (0, 1, 1)],
+    [(0, 0, 0), (0, 1, 0), (1, 1, 0), (1, 0, 0)],
+];
+
 fn main() -> Result<(), Box<dyn Error>> {
     let args: Vec<String> = std::env::args().collect();
 
@@ -12,205 +23,595 @@ fn main() -> Result<(), Box<dyn Error>> {
         println!("Total surface area: {}", droplet.total_surface_area());
         println!("External surface area: {}", droplet.external_surface_area());
 
+        for (i, pocket) in droplet.air_pockets().iter().enumerate() {
+            println!(
+                "Air pocket {}: volume {}, surface area {}, bounds {:?} to {:?}",
+                i + 1,
+                pocket.volume(),
+                pocket.surface_area,
+                pocket.bounding_box.min,
+                pocket.bounding_box.max
+            );
+        }
+
+        if let Some(mesh_path) = args.get(2) {
+            write_mesh(&droplet.mesh(Surface::External), mesh_path)?;
+        }
+
+        if let Some(air_pocket_mesh_path) = args.get(3) {
+            write_mesh(&droplet.mesh(Surface::AirPockets), air_pocket_mesh_path)?;
+        }
+
         Ok(())
     } else {
-        Err("Usage: day18 INPUT_FILE_PATH".into())
+        Err("Usage: day18 INPUT_FILE_PATH [MESH_PATH [AIR_POCKET_MESH_PATH]]".into())
     }
 }
 
+fn write_mesh(mesh: &Mesh, path: &str) -> Result<(), Box<dyn Error>> {
+    if path.ends_with(".obj") {
+        fs::write(path, mesh.to_obj())?;
+    } else if path.ends_with(".stl") {
+        fs::write(path, mesh.to_stl("droplet"))?;
+    } else {
+        return Err("Mesh path must end with .obj or .stl".into());
+    }
+
+    Ok(())
+}
+
+// Dense grids larger than this (in voxels) are stored sparsely instead
+const MAX_DENSE_VOLUME: usize = 1 << 24;
+
 struct LavaDroplet {
-    voxels: Vec<Voxel>,
-    bounds: (usize, usize, usize),
+    lava_voxels: Vec<(i32, i32, i32)>,
+    storage: VoxelStorage,
 }
 
 impl FromStr for LavaDroplet {
     type Err = Box<dyn Error>;
 
     fn from_str(string: &str) -> Result<Self, Self::Err> {
-        let lava_voxels: Vec<(usize, usize, usize)> = string
+        let lava_voxels: Vec<(i32, i32, i32)> = string
             .split('\n')
             .filter(|line| !line.is_empty())
             .map(
                 |line| match line.split(',').collect::<Vec<&str>>().as_slice() {
-                    [x, y, z] => Ok((x.parse()?, y.parse()?, z.parse()?)),
+                    [x, y, z] => Ok((x.trim().parse()?, y.trim().parse()?, z.trim().parse()?)),
                     _ => Err("Could not parse line".into()),
                 },
             )
             .collect::<Result<_, Box<dyn Error>>>()?;
 
-        let bounds = lava_voxels
+        let storage = match BoundingBox::enclosing(&lava_voxels) {
+            Some(bounding_box) if bounding_box.padded().volume() <= MAX_DENSE_VOLUME => {
+                Storage::Dense
+            }
+            _ => Storage::Sparse,
+        };
+
+        Ok(LavaDroplet::new(lava_voxels, storage))
+    }
+}
+
+impl LavaDroplet {
+    fn new(lava_voxels: Vec<(i32, i32, i32)>, storage: Storage) -> Self {
+        let storage = match storage {
+            Storage::Dense => VoxelStorage::Dense(VoxelGrid::new(&lava_voxels)),
+            Storage::Sparse => VoxelStorage::Sparse(LavaDroplet::sparse_voxels(&lava_voxels)),
+        };
+
+        LavaDroplet {
+            lava_voxels,
+            storage,
+        }
+    }
+
+    // Finds trapped air without visiting every voxel in the droplet's bounding box. Air that
+    // touches lava (on a face, edge, or corner) forms a thin "skin" around each group of lava
+    // voxels, and we can split the skin into connected pieces without ever leaving it. A piece
+    // is external if a straight line from it can escape past all of the lava, and a line that
+    // instead hits lava lands in another piece of skin in the same body of air. Any piece we can't
+    // connect to the outside that way is flood-filled to find the rest of its pocket.
+    fn sparse_voxels(lava_voxels: &[(i32, i32, i32)]) -> HashMap<(i32, i32, i32), Voxel> {
+        let lava: HashSet<(i32, i32, i32)> = lava_voxels.iter().copied().collect();
+
+        let mut voxels: HashMap<(i32, i32, i32), Voxel> = lava_voxels
             .iter()
-            .fold((0usize, 0usize, 0usize), |(b_x, b_y, b_z), (x, y, z)| {
-                (b_x.max(*x + 1), b_y.max(*y + 1), b_z.max(*z + 1))
-            });
+            .map(|&position| (position, Voxel::Lava))
+            .collect();
 
-        let mut droplet = LavaDroplet {
-            voxels: vec![Voxel::ExternalAir; bounds.0 * bounds.1 * bounds.2],
-            bounds,
+        let Some(bounding_box) = BoundingBox::enclosing(lava_voxels).map(|b| b.padded()) else {
+            return voxels;
         };
 
-        lava_voxels.iter().for_each(|&(x, y, z)| {
-            let index = droplet.index(x, y, z);
-            droplet.voxels[index] = Voxel::Lava;
-        });
+        // The x coordinates of lava voxels along each line parallel to the x axis
+        let mut lines: HashMap<(i32, i32), Vec<i32>> = HashMap::new();
 
-        let mut unvisited_air_voxels = HashSet::new();
+        for &(x, y, z) in lava_voxels {
+            lines.entry((y, z)).or_default().push(x);
+        }
+
+        lines.values_mut().for_each(|line| line.sort_unstable());
+
+        let skin: HashSet<(i32, i32, i32)> = lava_voxels
+            .iter()
+            .flat_map(|&(x, y, z)| {
+                (-1..=1).flat_map(move |d_x| {
+                    (-1..=1)
+                        .flat_map(move |d_y| (-1..=1).map(move |d_z| (x + d_x, y + d_y, z + d_z)))
+                })
+            })
+            .filter(|position| !lava.contains(position))
+            .collect();
+
+        // Label each connected piece of skin
+        let mut pieces: HashMap<(i32, i32, i32), usize> = HashMap::new();
+        let mut piece_voxels: Vec<Vec<(i32, i32, i32)>> = vec![];
+
+        for &start in &skin {
+            if pieces.contains_key(&start) {
+                continue;
+            }
+
+            let piece = piece_voxels.len();
+            let mut voxels = vec![start];
+            let mut exploration_stack = vec![start];
+
+            pieces.insert(start, piece);
 
-        for x in 0..droplet.bounds.0 {
-            for y in 0..droplet.bounds.1 {
-                for z in 0..droplet.bounds.2 {
-                    if !matches!(
-                        droplet.voxel(x as isize, y as isize, z as isize),
-                        Voxel::Lava
-                    ) {
-                        unvisited_air_voxels.insert((x, y, z));
+            while let Some(position) = exploration_stack.pop() {
+                for neighbor in LavaDroplet::neighbors(position) {
+                    if skin.contains(&neighbor) && !pieces.contains_key(&neighbor) {
+                        pieces.insert(neighbor, piece);
+                        voxels.push(neighbor);
+                        exploration_stack.push(neighbor);
                     }
                 }
             }
+
+            piece_voxels.push(voxels);
         }
 
-        while !unvisited_air_voxels.is_empty() {
-            let mut exploration_queue = vec![*unvisited_air_voxels.iter().next().unwrap()];
-            let mut explored_group = vec![];
-            let mut group_has_path_to_surface = false;
-
-            while !exploration_queue.is_empty() {
-                let (x, y, z) = exploration_queue.pop().unwrap();
-
-                droplet
-                    .neighbors(x, y, z)
-                    .into_iter()
-                    .filter(|(x, y, z)| {
-                        *x >= 0
-                            && *y >= 0
-                            && *z >= 0
-                            && (*x as usize) < droplet.bounds.0
-                            && (*y as usize) < droplet.bounds.1
-                            && (*z as usize) < droplet.bounds.2
-                    })
-                    .filter(|(x, y, z)| {
-                        unvisited_air_voxels.contains(&(*x as usize, *y as usize, *z as usize))
-                    })
-                    .for_each(|(x, y, z)| {
-                        exploration_queue.push((x as usize, y as usize, z as usize));
-
-                        if x == 0
-                            || y == 0
-                            || z == 0
-                            || (x as usize) == droplet.bounds.0
-                            || (y as usize) == droplet.bounds.1
-                            || (z as usize) == droplet.bounds.2
-                        {
-                            group_has_path_to_surface = true;
-                        }
-                    });
+        // Pieces of skin in the same body of air share a root; the extra element stands for the
+        // air outside the droplet
+        let outside = piece_voxels.len();
+        let mut parents: Vec<usize> = (0..=outside).collect();
 
-                unvisited_air_voxels.remove(&(x, y, z));
-                explored_group.push((x, y, z));
+        fn root(parents: &mut [usize], mut i: usize) -> usize {
+            while parents[i] != i {
+                parents[i] = parents[parents[i]];
+                i = parents[i];
             }
 
-            explored_group.into_iter().for_each(|(x, y, z)| {
-                let index = droplet.index(x, y, z);
+            i
+        }
 
-                droplet.voxels[index] = if group_has_path_to_surface {
-                    Voxel::ExternalAir
-                } else {
-                    Voxel::TrappedAir
-                }
-            });
+        for &(x, y, z) in &skin {
+            if lava.contains(&(x - 1, y, z)) {
+                continue;
+            }
+
+            // Look for the nearest lava in the negative x direction
+            let line = lines.get(&(y, z)).map(Vec::as_slice).unwrap_or(&[]);
+            let hit = line.partition_point(|&lava_x| lava_x < x);
+
+            let other = if hit == 0 {
+                outside
+            } else {
+                pieces[&(line[hit - 1] + 1, y, z)]
+            };
+
+            let (root_a, root_b) = (
+                root(&mut parents, pieces[&(x, y, z)]),
+                root(&mut parents, other),
+            );
+
+            parents[root_a] = root_b;
         }
 
-        Ok(droplet)
-    }
-}
+        let mut resolved: HashSet<usize> = HashSet::new();
 
-impl LavaDroplet {
-    fn index(&self, x: usize, y: usize, z: usize) -> usize {
-        (self.bounds.0 * self.bounds.1 * z) + (self.bounds.0 * y) + x
+        for (piece, piece_skin) in piece_voxels.iter().enumerate() {
+            if root(&mut parents, piece) == root(&mut parents, outside) || resolved.contains(&piece)
+            {
+                continue;
+            }
+
+            // Flood-fill the air around this piece; if it escapes the droplet's bounding box, it
+            // wasn't trapped after all
+            let mut visited: HashSet<(i32, i32, i32)> = piece_skin.iter().copied().collect();
+            let mut exploration_stack = piece_skin.clone();
+            let mut escaped = false;
+
+            while let Some(position) = exploration_stack.pop() {
+                if !bounding_box.contains(position) {
+                    escaped = true;
+                    break;
+                }
+
+                for neighbor in LavaDroplet::neighbors(position) {
+                    if !lava.contains(&neighbor) && visited.insert(neighbor) {
+                        exploration_stack.push(neighbor);
+                    }
+                }
+            }
+
+            resolved.extend(visited.iter().filter_map(|position| pieces.get(position)));
+
+            if !escaped {
+                voxels.extend(
+                    visited
+                        .into_iter()
+                        .map(|position| (position, Voxel::TrappedAir)),
+                );
+            }
+        }
+
+        voxels
     }
 
-    fn voxel(&self, x: isize, y: isize, z: isize) -> Voxel {
-        if x < 0
-            || y < 0
-            || z < 0
-            || x as usize >= self.bounds.0
-            || y as usize >= self.bounds.1
-            || z as usize >= self.bounds.2
-        {
-            Voxel::ExternalAir
-        } else {
-            self.voxels[self.index(x as usize, y as usize, z as usize)]
+    fn voxel(&self, position: (i32, i32, i32)) -> Voxel {
+        match &self.storage {
+            VoxelStorage::Dense(grid) => grid.voxel(position),
+            VoxelStorage::Sparse(voxels) => {
+                voxels.get(&position).copied().unwrap_or(Voxel::ExternalAir)
+            }
         }
     }
 
     fn total_surface_area(&self) -> u32 {
-        let mut surface_area = 0;
-
-        for x in 0..self.bounds.0 {
-            for y in 0..self.bounds.1 {
-                for z in 0..self.bounds.2 {
-                    if matches!(self.voxel(x as isize, y as isize, z as isize), Voxel::Lava) {
-                        surface_area += self
-                            .neighbors(x, y, z)
-                            .iter()
-                            .map(|&(neighbor_x, neighbor_y, neighbor_z)| {
-                                self.voxel(neighbor_x, neighbor_y, neighbor_z)
-                            })
-                            .filter(|voxel| !matches!(voxel, Voxel::Lava))
-                            .count() as u32;
+        self.lava_voxels
+            .iter()
+            .map(|&position| {
+                LavaDroplet::neighbors(position)
+                    .iter()
+                    .filter(|&&neighbor| !matches!(self.voxel(neighbor), Voxel::Lava))
+                    .count() as u32
+            })
+            .sum()
+    }
+
+    fn external_surface_area(&self) -> u32 {
+        self.lava_voxels
+            .iter()
+            .map(|&position| {
+                LavaDroplet::neighbors(position)
+                    .iter()
+                    .filter(|&&neighbor| matches!(self.voxel(neighbor), Voxel::ExternalAir))
+                    .count() as u32
+            })
+            .sum()
+    }
+
+    // Returns every enclosed pocket of air, ordered by the position of its first voxel
+    fn air_pockets(&self) -> Vec<AirPocket> {
+        let mut unvisited: HashSet<(i32, i32, i32)> = match &self.storage {
+            VoxelStorage::Dense(grid) => grid
+                .positions()
+                .filter(|&position| matches!(grid.voxel(position), Voxel::TrappedAir))
+                .collect(),
+            VoxelStorage::Sparse(voxels) => voxels
+                .iter()
+                .filter(|(_, voxel)| matches!(voxel, Voxel::TrappedAir))
+                .map(|(&position, _)| position)
+                .collect(),
+        };
+
+        let mut starts: Vec<(i32, i32, i32)> = unvisited.iter().copied().collect();
+        starts.sort_by_key(|&(x, y, z)| (z, y, x));
+
+        let mut pockets = vec![];
+
+        for start in starts {
+            if !unvisited.remove(&start) {
+                continue;
+            }
+
+            let mut voxels = vec![];
+            let mut surface_area = 0;
+            let mut exploration_stack = vec![start];
+
+            while let Some(position) = exploration_stack.pop() {
+                voxels.push(position);
+
+                // Trapped air can only border lava or more of the same pocket
+                for neighbor in LavaDroplet::neighbors(position) {
+                    if unvisited.remove(&neighbor) {
+                        exploration_stack.push(neighbor);
+                    } else if matches!(self.voxel(neighbor), Voxel::Lava) {
+                        surface_area += 1;
                     }
                 }
             }
+
+            pockets.push(AirPocket {
+                bounding_box: BoundingBox::enclosing(&voxels).expect("Pockets should not be empty"),
+                voxels,
+                surface_area,
+            });
         }
 
-        surface_area
+        pockets
     }
 
-    fn external_surface_area(&self) -> u32 {
-        let mut surface_area = 0;
-
-        for x in 0..self.bounds.0 {
-            for y in 0..self.bounds.1 {
-                for z in 0..self.bounds.2 {
-                    if matches!(self.voxel(x as isize, y as isize, z as isize), Voxel::Lava) {
-                        surface_area += self
-                            .neighbors(x, y, z)
-                            .iter()
-                            .map(|&(neighbor_x, neighbor_y, neighbor_z)| {
-                                self.voxel(neighbor_x, neighbor_y, neighbor_z)
-                            })
-                            .filter(|voxel| matches!(voxel, Voxel::ExternalAir))
-                            .count() as u32;
-                    }
+    fn mesh(&self, surface: Surface) -> Mesh {
+        let mut mesh = Mesh::default();
+        let mut vertex_indices = HashMap::new();
+
+        for &(x, y, z) in &self.lava_voxels {
+            for (direction, neighbor) in LavaDroplet::neighbors((x, y, z)).into_iter().enumerate() {
+                let exposed = matches!(
+                    (&surface, self.voxel(neighbor)),
+                    (Surface::External, Voxel::ExternalAir)
+                        | (Surface::AirPockets, Voxel::TrappedAir)
+                );
+
+                if exposed {
+                    let corners = FACE_CORNERS[direction].map(|(d_x, d_y, d_z)| {
+                        let corner = (x + d_x, y + d_y, z + d_z);
+
+                        *vertex_indices.entry(corner).or_insert_with(|| {
+                            mesh.vertices.push(corner);
+                            mesh.vertices.len() - 1
+                        })
+                    });
+
+                    let normal = (neighbor.0 - x, neighbor.1 - y, neighbor.2 - z);
+
+                    mesh.faces.push((corners, normal));
                 }
             }
         }
 
-        surface_area
+        mesh
     }
 
-    fn neighbors(&self, x: usize, y: usize, z: usize) -> [(isize, isize, isize); 6] {
+    fn neighbors((x, y, z): (i32, i32, i32)) -> [(i32, i32, i32); 6] {
         [
-            (x as isize + 1, y as isize, z as isize),
-            (x as isize - 1, y as isize, z as isize),
-            (x as isize, y as isize + 1, z as isize),
-            (x as isize, y as isize - 1, z as isize),
-            (x as isize, y as isize, z as isize + 1),
-            (x as isize, y as isize, z as isize - 1),
+            (x + 1, y, z),
+            (x - 1, y, z),
+            (x, y + 1, z),
+            (x, y - 1, z),
+            (x, y, z + 1),
+            (x, y, z - 1),
         ]
     }
 }
 
-#[derive(Copy, Clone)]
+enum Storage {
+    Dense,
+    Sparse,
+}
+
+enum VoxelStorage {
+    Dense(VoxelGrid),
+
+    // Only lava and trapped air are stored; everything else is external air
+    Sparse(HashMap<(i32, i32, i32), Voxel>),
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+struct BoundingBox {
+    min: (i32, i32, i32),
+    max: (i32, i32, i32),
+}
+
+impl BoundingBox {
+    fn enclosing(positions: &[(i32, i32, i32)]) -> Option<Self> {
+        let (&first, rest) = positions.split_first()?;
+
+        Some(rest.iter().fold(
+            BoundingBox {
+                min: first,
+                max: first,
+            },
+            |bounding_box, &(x, y, z)| BoundingBox {
+                min: (
+                    bounding_box.min.0.min(x),
+                    bounding_box.min.1.min(y),
+                    bounding_box.min.2.min(z),
+                ),
+                max: (
+                    bounding_box.max.0.max(x),
+                    bounding_box.max.1.max(y),
+                    bounding_box.max.2.max(z),
+                ),
+            },
+        ))
+    }
+
+    fn padded(&self) -> Self {
+        BoundingBox {
+            min: (self.min.0 - 1, self.min.1 - 1, self.min.2 - 1),
+            max: (self.max.0 + 1, self.max.1 + 1, self.max.2 + 1),
+        }
+    }
+
+    fn size(&self) -> (usize, usize, usize) {
+        (
+            self.max.0.abs_diff(self.min.0) as usize + 1,
+            self.max.1.abs_diff(self.min.1) as usize + 1,
+            self.max.2.abs_diff(self.min.2) as usize + 1,
+        )
+    }
+
+    fn volume(&self) -> usize {
+        let (x, y, z) = self.size();
+        x.saturating_mul(y).saturating_mul(z)
+    }
+
+    fn contains(&self, (x, y, z): (i32, i32, i32)) -> bool {
+        (self.min.0..=self.max.0).contains(&x)
+            && (self.min.1..=self.max.1).contains(&y)
+            && (self.min.2..=self.max.2).contains(&z)
+    }
+}
+
+// Every voxel in the bounding box of a group of lava voxels, plus a one-voxel shell of air on every
+// side
+struct VoxelGrid {
+    bounding_box: BoundingBox,
+    size: (usize, usize, usize),
+    voxels: Vec<Voxel>,
+}
+
+impl VoxelGrid {
+    fn new(lava_voxels: &[(i32, i32, i32)]) -> Self {
+        let bounding_box = BoundingBox::enclosing(lava_voxels)
+            .map(|bounding_box| bounding_box.padded())
+            .unwrap_or(BoundingBox {
+                min: (0, 0, 0),
+                max: (0, 0, 0),
+            });
+
+        let size = bounding_box.size();
+
+        let mut grid = VoxelGrid {
+            bounding_box,
+            size,
+            voxels: vec![Voxel::TrappedAir; size.0 * size.1 * size.2],
+        };
+
+        for &position in lava_voxels {
+            let index = grid.index(position).expect("Lava should be in bounds");
+            grid.voxels[index] = Voxel::Lava;
+        }
+
+        // The padding shell is all air and surrounds everything else, so any air we can reach from
+        // one of its corners is external
+        let mut exploration_stack = vec![bounding_box.min];
+        grid.voxels[0] = Voxel::ExternalAir;
+
+        while let Some(position) = exploration_stack.pop() {
+            for neighbor in LavaDroplet::neighbors(position) {
+                if let Some(index) = grid.index(neighbor) {
+                    if matches!(grid.voxels[index], Voxel::TrappedAir) {
+                        grid.voxels[index] = Voxel::ExternalAir;
+                        exploration_stack.push(neighbor);
+                    }
+                }
+            }
+        }
+
+        grid
+    }
+
+    fn index(&self, (x, y, z): (i32, i32, i32)) -> Option<usize> {
+        let (min_x, min_y, min_z) = self.bounding_box.min;
+        let (max_x, max_y, max_z) = self.bounding_box.max;
+
+        if x < min_x || y < min_y || z < min_z || x > max_x || y > max_y || z > max_z {
+            None
+        } else {
+            let (x, y, z) = (
+                x.abs_diff(min_x) as usize,
+                y.abs_diff(min_y) as usize,
+                z.abs_diff(min_z) as usize,
+            );
+
+            Some((self.size.0 * self.size.1 * z) + (self.size.0 * y) + x)
+        }
+    }
+
+    fn voxel(&self, position: (i32, i32, i32)) -> Voxel {
+        self.index(position)
+            .map(|index| self.voxels[index])
+            .unwrap_or(Voxel::ExternalAir)
+    }
+
+    fn positions(&self) -> impl Iterator<Item = (i32, i32, i32)> + '_ {
+        let (min_x, min_y, min_z) = self.bounding_box.min;
+        let (max_x, max_y, max_z) = self.bounding_box.max;
+
+        (min_z..=max_z).flat_map(move |z| {
+            (min_y..=max_y).flat_map(move |y| (min_x..=max_x).map(move |x| (x, y, z)))
+        })
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
 enum Voxel {
     ExternalAir,
     TrappedAir,
     Lava,
 }
 
+struct AirPocket {
+    voxels: Vec<(i32, i32, i32)>,
+    bounding_box: BoundingBox,
+
+    // The number of lava faces that touch this pocket
+    surface_area: u32,
+}
+
+impl AirPocket {
+    fn volume(&self) -> usize {
+        self.voxels.len()
+    }
+}
+
+enum Surface {
+    // Faces of lava voxels that touch external air
+    External,
+
+    // Faces of lava voxels that touch trapped air; these face into the air pockets
+    AirPockets,
+}
+
+#[derive(Debug, Default)]
+struct Mesh {
+    vertices: Vec<(i32, i32, i32)>,
+
+    // Each face is a square described by the indices of its corners (counter-clockwise as seen from
+    // outside the surface) and its outward-facing normal
+    faces: Vec<([usize; 4], (i32, i32, i32))>,
+}
+
+impl Mesh {
+    fn to_obj(&self) -> String {
+        let mut obj = String::new();
+
+        self.vertices
+            .iter()
+            .for_each(|(x, y, z)| obj.push_str(&format!("v {} {} {}\n", x, y, z)));
+
+        // OBJ indices start at 1
+        self.faces.iter().for_each(|([a, b, c, d], _)| {
+            obj.push_str(&format!("f {} {} {} {}\n", a + 1, b + 1, c + 1, d + 1))
+        });
+
+        obj
+    }
+
+    fn to_stl(&self, name: &str) -> String {
+        let mut stl = format!("solid {}\n", name);
+
+        for ([a, b, c, d], (n_x, n_y, n_z)) in &self.faces {
+            // STL only supports triangles, so split each square face in two
+            for triangle in [[a, b, c], [a, c, d]] {
+                stl.push_str(&format!("  facet normal {} {} {}\n", n_x, n_y, n_z));
+                stl.push_str("    outer loop\n");
+
+                for &&vertex in &triangle {
+                    let (x, y, z) = self.vertices[vertex];
+                    stl.push_str(&format!("      vertex {} {} {}\n", x, y, z));
+                }
+
+                stl.push_str("    endloop\n");
+                stl.push_str("  endfacet\n");
+            }
+        }
+
+        stl.push_str(&format!("endsolid {}\n", name));
+        stl
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
+    use advent_of_code_2022::xorshift::XorShift;
     use indoc::indoc;
 
     const TEST_DROPLET: &str = indoc! {"
@@ -254,4 +655,270 @@ mod test {
             assert_eq!(58, droplet.external_surface_area());
         }
     }
+
+    #[test]
+    fn test_signed_coordinates() {
+        let shifted: String = TEST_DROPLET
+            .lines()
+            .map(|line| {
+                let coordinates: Vec<i32> = line.split(',').map(|c| c.parse().unwrap()).collect();
+
+                format!(
+                    "{},{},{}\n",
+                    coordinates[0] - 10,
+                    coordinates[1] - 5,
+                    coordinates[2] - 100
+                )
+            })
+            .collect();
+
+        let droplet = LavaDroplet::from_str(&shifted).unwrap();
+        assert_eq!(64, droplet.total_surface_area());
+        assert_eq!(58, droplet.external_surface_area());
+        assert_eq!(Voxel::TrappedAir, droplet.voxel((-8, -3, -95)));
+
+        // Lava touching the origin from every side shouldn't confuse the edges of the grid
+        let droplet = LavaDroplet::from_str("0,0,0\n-1,0,0\n0,-1,0").unwrap();
+        assert_eq!(14, droplet.external_surface_area());
+    }
+
+    #[test]
+    fn test_sparse_storage() {
+        let dense = LavaDroplet::from_str(TEST_DROPLET).unwrap();
+        let sparse = LavaDroplet::new(dense.lava_voxels.clone(), Storage::Sparse);
+
+        assert!(matches!(dense.storage, VoxelStorage::Dense(_)));
+        assert_eq!(dense.total_surface_area(), sparse.total_surface_area());
+        assert_eq!(
+            dense.external_surface_area(),
+            sparse.external_surface_area()
+        );
+
+        // Two copies of the test droplet far enough apart that a dense grid would be enormous
+        let far_apart: String = TEST_DROPLET
+            .lines()
+            .flat_map(|line| [line.to_string(), format!("{},1000000,-1000000", line)])
+            .map(|line| {
+                let coordinates: Vec<i32> =
+                    line.split(',').map(|c| c.parse::<i32>().unwrap()).collect();
+
+                format!(
+                    "{},{},{}\n",
+                    coordinates[0],
+                    coordinates[1] + coordinates.get(3).unwrap_or(&0),
+                    coordinates[2] + coordinates.get(4).unwrap_or(&0)
+                )
+            })
+            .collect();
+
+        let droplet = LavaDroplet::from_str(&far_apart).unwrap();
+        assert!(matches!(droplet.storage, VoxelStorage::Sparse(_)));
+        assert_eq!(128, droplet.total_surface_area());
+        assert_eq!(116, droplet.external_surface_area());
+        assert_eq!(Voxel::TrappedAir, droplet.voxel((2, 1000002, -999995)));
+    }
+
+    #[test]
+    fn test_sparse_storage_spread_out() {
+        // Voxels just close enough to touch one another's padding, stretched across a huge volume
+        let lava_voxels: Vec<(i32, i32, i32)> = (0..250).map(|i| (3 * i, 3 * i, 3 * i)).collect();
+        let droplet = LavaDroplet::new(lava_voxels, Storage::Sparse);
+
+        assert_eq!(1500, droplet.external_surface_area());
+        assert!(droplet.air_pockets().is_empty());
+    }
+
+    #[test]
+    fn test_sparse_storage_matches_dense_storage() {
+        let mut random = XorShift::new(0x2545f4914f6cdd1d);
+
+        for _ in 0..100 {
+            let mut lava_voxels = HashSet::new();
+
+            // Hollow boxes (some with holes punched in them) make for plenty of pockets
+            for _ in 0..random.below(4) {
+                let min = (
+                    random.below(12) as i32,
+                    random.below(12) as i32,
+                    random.below(12) as i32,
+                );
+
+                let size = (
+                    random.below(5) as i32 + 3,
+                    random.below(5) as i32 + 3,
+                    random.below(5) as i32 + 3,
+                );
+
+                for x in 0..size.0 {
+                    for y in 0..size.1 {
+                        for z in 0..size.2 {
+                            let on_wall = x == 0
+                                || y == 0
+                                || z == 0
+                                || x == size.0 - 1
+                                || y == size.1 - 1
+                                || z == size.2 - 1;
+
+                            if on_wall && random.below(40) != 0 {
+                                lava_voxels.insert((min.0 + x, min.1 + y, min.2 + z));
+                            }
+                        }
+                    }
+                }
+            }
+
+            for _ in 0..random.below(60) {
+                lava_voxels.insert((
+                    random.below(16) as i32,
+                    random.below(16) as i32,
+                    random.below(16) as i32,
+                ));
+            }
+
+            let lava_voxels: Vec<(i32, i32, i32)> = lava_voxels.into_iter().collect();
+
+            let dense = LavaDroplet::new(lava_voxels.clone(), Storage::Dense);
+            let sparse = LavaDroplet::new(lava_voxels, Storage::Sparse);
+
+            for x in -1..=20 {
+                for y in -1..=20 {
+                    for z in -1..=20 {
+                        assert_eq!(dense.voxel((x, y, z)), sparse.voxel((x, y, z)));
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_air_pockets() {
+        {
+            let droplet = LavaDroplet::from_str(TEST_DROPLET).unwrap();
+            let pockets = droplet.air_pockets();
+
+            assert_eq!(1, pockets.len());
+            assert_eq!(1, pockets[0].volume());
+            assert_eq!(6, pockets[0].surface_area);
+            assert_eq!(
+                BoundingBox {
+                    min: (2, 2, 5),
+                    max: (2, 2, 5)
+                },
+                pockets[0].bounding_box
+            );
+        }
+
+        {
+            // A hollow 4x4x4 cube with a 2x2x2 cavity and a hollow 3x3x3 cube with a single-voxel
+            // cavity, placed far enough apart to be stored sparsely
+            let mut lava_voxels = vec![];
+
+            for x in 0..4 {
+                for y in 0..4 {
+                    for z in 0..4 {
+                        if ![x, y, z].iter().all(|c| (1..=2).contains(c)) {
+                            lava_voxels.push((x - 2, y - 2, z - 2));
+                        }
+
+                        if x < 3 && y < 3 && z < 3 && (x, y, z) != (1, 1, 1) {
+                            lava_voxels.push((x + 10_000_000, y, z));
+                        }
+                    }
+                }
+            }
+
+            let droplet = LavaDroplet::new(lava_voxels, Storage::Sparse);
+            let pockets = droplet.air_pockets();
+
+            assert_eq!(2, pockets.len());
+
+            assert_eq!(8, pockets[0].volume());
+            assert_eq!(24, pockets[0].surface_area);
+            assert_eq!(
+                BoundingBox {
+                    min: (-1, -1, -1),
+                    max: (0, 0, 0)
+                },
+                pockets[0].bounding_box
+            );
+
+            assert_eq!(1, pockets[1].volume());
+            assert_eq!(6, pockets[1].surface_area);
+
+            assert_eq!(
+                droplet.total_surface_area() - droplet.external_surface_area(),
+                pockets
+                    .iter()
+                    .map(|pocket| pocket.surface_area)
+                    .sum::<u32>()
+            );
+
+            assert_eq!(Voxel::TrappedAir, droplet.voxel((0, -1, 0)));
+            assert_eq!(Voxel::Lava, droplet.voxel((1, -1, 0)));
+            assert_eq!(Voxel::ExternalAir, droplet.voxel((2, -1, 0)));
+            assert_eq!(Voxel::ExternalAir, droplet.voxel((5_000_000, 0, 0)));
+        }
+    }
+
+    #[test]
+    fn test_mesh() {
+        {
+            let droplet = LavaDroplet::from_str("1,1,1").unwrap();
+            let mesh = droplet.mesh(Surface::External);
+
+            assert_eq!(8, mesh.vertices.len());
+            assert_eq!(6, mesh.faces.len());
+            assert!(droplet.mesh(Surface::AirPockets).faces.is_empty());
+
+            // Every face's corners should wind counter-clockwise around its normal
+            for ([a, b, c, _], normal) in &mesh.faces {
+                let (a, b, c) = (mesh.vertices[*a], mesh.vertices[*b], mesh.vertices[*c]);
+                let (u, v) = (
+                    (b.0 - a.0, b.1 - a.1, b.2 - a.2),
+                    (c.0 - a.0, c.1 - a.1, c.2 - a.2),
+                );
+
+                assert_eq!(
+                    *normal,
+                    (
+                        u.1 * v.2 - u.2 * v.1,
+                        u.2 * v.0 - u.0 * v.2,
+                        u.0 * v.1 - u.1 * v.0
+                    )
+                );
+            }
+        }
+
+        {
+            let droplet = LavaDroplet::from_str(TEST_DROPLET).unwrap();
+
+            assert_eq!(58, droplet.mesh(Surface::External).faces.len());
+            assert_eq!(6, droplet.mesh(Surface::AirPockets).faces.len());
+        }
+    }
+
+    #[test]
+    fn test_mesh_export() {
+        let mesh = LavaDroplet::from_str("1,1,1\n2,1,1")
+            .unwrap()
+            .mesh(Surface::External);
+
+        let obj = mesh.to_obj();
+        assert_eq!(
+            12,
+            obj.lines().filter(|line| line.starts_with("v ")).count()
+        );
+        assert_eq!(
+            10,
+            obj.lines().filter(|line| line.starts_with("f ")).count()
+        );
+        assert!(obj.contains("v 3 2 2\n"));
+
+        let stl = mesh.to_stl("droplet");
+        assert!(stl.starts_with("solid droplet\n"));
+        assert!(stl.ends_with("endsolid droplet\n"));
+        assert_eq!(20, stl.matches("facet normal").count());
+        assert_eq!(60, stl.matches("vertex").count());
+        assert!(stl.contains("  facet normal 1 0 0\n    outer loop\n      vertex 3 1 1\n"));
+    }
 }
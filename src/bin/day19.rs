@@ -1,281 +1,622 @@
 use lazy_static::lazy_static;
 use regex::Regex;
 use std::cmp::Ordering;
-use std::collections::HashSet;
 use std::error::Error;
+use std::fmt::{Display, Formatter};
 use std::fs;
-use std::ops::{Add, Sub};
+use std::ops::{Add, Mul, Sub};
 use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+use std::thread;
+use std::time::{Duration, Instant};
 
 fn main() -> Result<(), Box<dyn Error>> {
     let args: Vec<String> = std::env::args().collect();
 
     if let Some(path) = args.get(1) {
         let factory = RobotFactory::from_str(fs::read_to_string(path)?.as_str())?;
+        let threads = std::thread::available_parallelism()?.get();
+
+        let evaluations = factory.evaluate(factory.blueprints.len(), 24, threads);
+
+        for evaluation in &evaluations {
+            println!(
+                "Blueprint {}: {} geodes after 24 minutes ({:?})",
+                evaluation.blueprint, evaluation.geodes, evaluation.elapsed
+            );
+        }
 
         println!(
             "Sum of quality levels of blueprints after 24 minutes: {}",
-            factory.quality_level_sum(24)
+            RobotFactory::quality_level_sum(&evaluations)
         );
 
         println!(
             "Product of geodes from first three plans after 32 minutes: {}",
-            factory.optimal_geode_product(3, 32)
+            RobotFactory::geode_product(&factory.evaluate(3, 32, threads))
         );
 
+        if let Some(blueprint_number) = args.get(2) {
+            let blueprint_number: usize = blueprint_number.parse()?;
+            let time_limit = args.get(3).map(|arg| arg.parse()).unwrap_or(Ok(24))?;
+
+            let blueprint = blueprint_number
+                .checked_sub(1)
+                .and_then(|i| factory.blueprints.get(i))
+                .ok_or("No such blueprint")?;
+
+            let target = match args.get(4) {
+                Some(name) => blueprint.resource(name).ok_or("No such resource")?,
+                None => blueprint.default_target(),
+            };
+
+            println!();
+            print!("{}", blueprint.optimal_plan(target, time_limit));
+        }
+
         Ok(())
     } else {
-        Err("Usage: day19 INPUT_FILE_PATH".into())
+        Err("Usage: day19 INPUT_FILE_PATH [BLUEPRINT_NUMBER [TIME_LIMIT [TARGET_RESOURCE]]]".into())
     }
 }
 
+// The most kinds of resources a single blueprint can use
+const MAX_RESOURCES: usize = 8;
+
 struct RobotFactory {
     blueprints: Vec<Blueprint>,
 }
 
 impl RobotFactory {
-    fn quality_level_sum(&self, time_limit: u32) -> u32 {
-        self.blueprints
+    // Optimizes the first `plans` blueprints, spreading the work across the given number of
+    // threads; results are always returned in blueprint order
+    fn evaluate(&self, plans: usize, time_limit: u32, threads: usize) -> Vec<Evaluation> {
+        let blueprints = &self.blueprints[..plans.min(self.blueprints.len())];
+        let next_blueprint = AtomicUsize::new(0);
+
+        let mut evaluations: Vec<Evaluation> = thread::scope(|scope| {
+            let workers: Vec<_> = (0..threads.max(1))
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut evaluations = vec![];
+
+                        // Blueprints can take wildly different amounts of time to optimize, so
+                        // workers claim them one at a time instead of splitting them up in advance
+                        loop {
+                            let i = next_blueprint.fetch_add(1, AtomicOrdering::Relaxed);
+
+                            if let Some(blueprint) = blueprints.get(i) {
+                                let start = Instant::now();
+                                let geodes = blueprint.optimize_geodes(time_limit);
+
+                                evaluations.push(Evaluation {
+                                    blueprint: i + 1,
+                                    geodes,
+                                    elapsed: start.elapsed(),
+                                });
+                            } else {
+                                break;
+                            }
+                        }
+
+                        evaluations
+                    })
+                })
+                .collect();
+
+            workers
+                .into_iter()
+                .flat_map(|worker| worker.join().expect("Worker thread should not panic"))
+                .collect()
+        });
+
+        evaluations.sort_by_key(|evaluation| evaluation.blueprint);
+        evaluations
+    }
+
+    fn quality_level_sum(evaluations: &[Evaluation]) -> u32 {
+        evaluations
             .iter()
-            .map(|blueprint| blueprint.optimize_geodes(time_limit) as u32)
-            .enumerate()
-            .map(|(i, geodes)| (i as u32 + 1) * geodes)
+            .map(|evaluation| evaluation.blueprint as u32 * evaluation.geodes as u32)
             .sum()
     }
 
-    fn optimal_geode_product(&self, plans: usize, time_limit: u32) -> u32 {
-        self.blueprints
+    fn geode_product(evaluations: &[Evaluation]) -> u32 {
+        evaluations
             .iter()
-            .take(plans)
-            .map(|blueprint| blueprint.optimize_geodes(time_limit) as u32)
+            .map(|evaluation| evaluation.geodes as u32)
             .product()
     }
 }
 
+#[derive(Debug)]
+struct Evaluation {
+    // The number of the evaluated blueprint, starting from 1
+    blueprint: usize,
+    geodes: u16,
+    elapsed: Duration,
+}
+
 impl FromStr for RobotFactory {
     type Err = Box<dyn Error>;
 
     fn from_str(string: &str) -> Result<Self, Self::Err> {
-        let blueprints: Vec<Blueprint> = string
-            .lines()
-            .map(Blueprint::from_str)
-            .collect::<Result<_, _>>()?;
+        // Sentence-style blueprints take one line each, while tables are separated by blank lines
+        let blueprints: Vec<Blueprint> = if string.trim_start().starts_with("Blueprint") {
+            string
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .map(Blueprint::from_str)
+                .collect::<Result<_, _>>()?
+        } else {
+            string
+                .split("\n\n")
+                .filter(|table| !table.trim().is_empty())
+                .map(Blueprint::from_str)
+                .collect::<Result<_, _>>()?
+        };
 
         Ok(RobotFactory { blueprints })
     }
 }
 
 struct Blueprint {
-    ore_robot_cost: Resources,
-    clay_robot_cost: Resources,
-    obsidian_robot_cost: Resources,
-    geode_robot_cost: Resources,
+    resource_names: Vec<String>,
+
+    // The cost of the robot that produces each resource, in the same order as `resource_names`
+    robot_costs: Vec<Resources>,
 }
 
 impl Blueprint {
+    fn resource(&self, name: &str) -> Option<usize> {
+        self.resource_names
+            .iter()
+            .position(|resource_name| resource_name == name)
+            .or_else(|| {
+                // Costs are often written in the plural ("7 obsidian," but "2 geodes")
+                name.strip_suffix('s').and_then(|singular| {
+                    self.resource_names
+                        .iter()
+                        .position(|resource_name| resource_name == singular)
+                })
+            })
+    }
+
+    // By convention, the most valuable resource comes last
+    fn default_target(&self) -> usize {
+        self.resource_names.len() - 1
+    }
+
     fn optimize_geodes(&self, time_limit: u32) -> u16 {
-        const PRACTICALLY_UNLIMITED: u16 = u16::MAX;
-
-        let max_spend_rates = Resources {
-            ore: self
-                .clay_robot_cost
-                .ore
-                .max(self.obsidian_robot_cost.ore)
-                .max(self.geode_robot_cost.ore),
-            clay: self.obsidian_robot_cost.clay,
-            obsidian: self.geode_robot_cost.obsidian,
-            geodes: PRACTICALLY_UNLIMITED,
+        self.optimal_plan(self.default_target(), time_limit).amount
+    }
+
+    fn optimal_plan(&self, target: usize, time_limit: u32) -> BuildPlan {
+        // We can only build one robot per minute, so there's never any point in having more robots
+        // of a kind than we could possibly spend in a single minute
+        let mut max_robots = Resources::default();
+
+        for resource in 0..self.resource_names.len() {
+            max_robots.0[resource] = if resource == target {
+                u16::MAX
+            } else {
+                self.robot_costs
+                    .iter()
+                    .map(|cost| cost.0[resource])
+                    .max()
+                    .unwrap_or(0)
+            };
+        }
+
+        let mut best_plan = BuildPlan {
+            resource_names: self.resource_names.clone(),
+            target,
+            time_limit: time_limit as u16,
+            builds: vec![],
+            amount: 0,
         };
 
-        let mut production_states = HashSet::from([ProductionState::default()]);
+        self.explore(
+            &ProductionState::default(),
+            time_limit as u16,
+            &max_robots,
+            &mut vec![],
+            &mut best_plan,
+        );
 
-        for time in 0..time_limit {
-            let time_remaining = (time_limit - time) as u16;
+        best_plan
+    }
 
-            let mut next_production_states = HashSet::new();
+    // Rather than considering every possible action each minute, jump straight to the next robot we
+    // could choose to build, waiting as long as it takes to afford it
+    fn explore(
+        &self,
+        production_state: &ProductionState,
+        time_remaining: u16,
+        max_robots: &Resources,
+        builds: &mut Vec<Build>,
+        best_plan: &mut BuildPlan,
+    ) {
+        let target = best_plan.target;
+
+        // Whatever else happens, our existing target robots will keep working until time runs out
+        let amount = production_state.resources.0[target]
+            + production_state.robots.0[target] * time_remaining;
+
+        if amount > best_plan.amount {
+            best_plan.amount = amount;
+            best_plan.builds = builds.clone();
+        }
 
-            for production_state in &production_states {
-                if production_state.robots.ore != PRACTICALLY_UNLIMITED
-                    && self.ore_robot_cost <= production_state.resources
-                {
-                    const ORE_ROBOT: Resources = Resources {
-                        ore: 1,
-                        clay: 0,
-                        obsidian: 0,
-                        geodes: 0,
-                    };
-
-                    let mut next_state = ProductionState {
-                        robots: production_state.robots + ORE_ROBOT,
-                        resources: production_state.resources - self.ore_robot_cost
-                            + production_state.robots,
-                    };
-
-                    // Coalesce all "more than we can possibly spend" states
-                    if next_state.resources.ore + (next_state.robots.ore * (time_remaining - 1))
-                        >= max_spend_rates.ore * time_remaining
-                    {
-                        next_state.robots.ore = PRACTICALLY_UNLIMITED;
-                        next_state.resources.ore = PRACTICALLY_UNLIMITED;
-                    }
-
-                    next_production_states.insert(next_state);
+        if self.upper_bound(production_state, target, time_remaining) <= best_plan.amount {
+            return;
+        }
+
+        // Exploring target robots first finds good plans (and tightens our bounds) sooner
+        let robots = std::iter::once(target).chain(
+            (0..self.resource_names.len())
+                .rev()
+                .filter(|&r| r != target),
+        );
+
+        for robot in robots {
+            if production_state.robots.0[robot] >= max_robots.0[robot] {
+                continue;
+            }
+
+            let cost = self.robot_costs[robot];
+
+            if let Some(wait) = production_state.time_to_afford(&cost) {
+                // A robot finished in the last minute can't produce anything
+                if wait + 1 >= time_remaining {
+                    continue;
                 }
 
-                if production_state.robots.clay != PRACTICALLY_UNLIMITED
-                    && self.clay_robot_cost <= production_state.resources
-                {
-                    const CLAY_ROBOT: Resources = Resources {
-                        ore: 0,
-                        clay: 1,
-                        obsidian: 0,
-                        geodes: 0,
-                    };
-
-                    let mut next_state = ProductionState {
-                        robots: production_state.robots + CLAY_ROBOT,
-                        resources: production_state.resources - self.clay_robot_cost
-                            + production_state.robots,
-                    };
-
-                    // Coalesce all "more than we can possibly spend" states
-                    if next_state.resources.clay + (next_state.robots.clay * (time_remaining - 1))
-                        >= max_spend_rates.clay * time_remaining
-                    {
-                        next_state.robots.clay = PRACTICALLY_UNLIMITED;
-                        next_state.resources.clay = PRACTICALLY_UNLIMITED;
-                    }
-
-                    next_production_states.insert(next_state);
+                let mut next_state = ProductionState {
+                    robots: production_state.robots,
+                    resources: production_state.resources + production_state.robots * (wait + 1)
+                        - cost,
+                };
+
+                next_state.robots.0[robot] += 1;
+
+                builds.push(Build {
+                    minute: best_plan.time_limit - time_remaining + wait + 1,
+                    robot,
+                    cost,
+                });
+
+                self.explore(
+                    &next_state,
+                    time_remaining - wait - 1,
+                    max_robots,
+                    builds,
+                    best_plan,
+                );
+
+                builds.pop();
+            }
+        }
+    }
+
+    // Optimistically assumes we can build one of every other kind of robot for free every minute,
+    // and a target robot whenever we have enough of everything else to pay for it
+    fn upper_bound(
+        &self,
+        production_state: &ProductionState,
+        target: usize,
+        time_remaining: u16,
+    ) -> u16 {
+        let mut robots = production_state.robots;
+        let mut resources = production_state.resources;
+
+        let mut cost = self.robot_costs[target];
+        cost.0[target] = 0;
+
+        for _ in 0..time_remaining {
+            let can_afford = cost <= resources;
+
+            resources = resources + robots;
+
+            for resource in 0..self.resource_names.len() {
+                if resource != target {
+                    robots.0[resource] += 1;
                 }
+            }
 
-                if production_state.robots.obsidian != PRACTICALLY_UNLIMITED
-                    && self.obsidian_robot_cost <= production_state.resources
-                {
-                    const OBSIDIAN_ROBOT: Resources = Resources {
-                        ore: 0,
-                        clay: 0,
-                        obsidian: 1,
-                        geodes: 0,
-                    };
-
-                    let mut next_state = ProductionState {
-                        robots: production_state.robots + OBSIDIAN_ROBOT,
-                        resources: production_state.resources - self.obsidian_robot_cost
-                            + production_state.robots,
-                    };
-
-                    // Coalesce all "more than we can possibly spend" states
-                    if next_state.resources.obsidian
-                        + (next_state.robots.obsidian * (time_remaining - 1))
-                        >= max_spend_rates.obsidian * time_remaining
-                    {
-                        next_state.robots.obsidian = PRACTICALLY_UNLIMITED;
-                        next_state.resources.obsidian = PRACTICALLY_UNLIMITED;
-                    }
-
-                    next_production_states.insert(next_state);
+            if can_afford {
+                resources = resources - cost;
+                robots.0[target] += 1;
+            }
+        }
+
+        resources.0[target]
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+struct Build {
+    // The minute (starting from 1) in which we spend resources to start building the robot
+    minute: u16,
+    robot: usize,
+    cost: Resources,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+struct BuildPlan {
+    resource_names: Vec<String>,
+    target: usize,
+    time_limit: u16,
+    builds: Vec<Build>,
+
+    // The amount of the target resource we'll have at the end of the plan
+    amount: u16,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+struct MinuteSummary {
+    minute: u16,
+    build: Option<Build>,
+
+    // The robots collecting resources during this minute, not counting any that are still being built
+    robots: Resources,
+
+    // Resources in inventory at the end of this minute
+    resources: Resources,
+}
+
+impl BuildPlan {
+    fn minutes(&self) -> Vec<MinuteSummary> {
+        let mut production_state = ProductionState::default();
+        let mut builds = self.builds.iter().peekable();
+
+        (1..=self.time_limit)
+            .map(|minute| {
+                let build = builds.next_if(|build| build.minute == minute).copied();
+                let robots = production_state.robots;
+
+                production_state.resources = production_state.resources + robots;
+
+                if let Some(build) = build {
+                    production_state.resources = production_state.resources - build.cost;
+                    production_state.robots.0[build.robot] += 1;
+                }
+
+                MinuteSummary {
+                    minute,
+                    build,
+                    robots,
+                    resources: production_state.resources,
                 }
+            })
+            .collect()
+    }
 
-                if self.geode_robot_cost <= production_state.resources {
-                    const GEODE_ROBOT: Resources = Resources {
-                        ore: 0,
-                        clay: 0,
-                        obsidian: 0,
-                        geodes: 1,
-                    };
-
-                    next_production_states.insert(ProductionState {
-                        robots: production_state.robots + GEODE_ROBOT,
-                        resources: production_state.resources - self.geode_robot_cost
-                            + production_state.robots,
-                    });
+    // Geodes get cracked rather than collected, and are the only resource that comes in countable
+    // units; everything else is collected in bulk
+    fn robot_name(&self, resource: usize) -> String {
+        match self.resource_names[resource].as_str() {
+            "geode" => String::from("geode-cracking"),
+            name => format!("{}-collecting", name),
+        }
+    }
+}
+
+impl Display for BuildPlan {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let quantity =
+            |amount: u16, resource: usize, open: bool| match self.resource_names[resource].as_str()
+            {
+                "geode" => format!(
+                    "{} {}geode{}",
+                    amount,
+                    if open { "open " } else { "" },
+                    if amount == 1 { "" } else { "s" }
+                ),
+                name => format!("{} {}", amount, name),
+            };
+
+        for (i, summary) in self.minutes().iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+
+            writeln!(f, "== Minute {} ==", summary.minute)?;
+
+            if let Some(build) = summary.build {
+                let cost = (0..self.resource_names.len())
+                    .filter(|&resource| build.cost.0[resource] > 0)
+                    .map(|resource| quantity(build.cost.0[resource], resource, false))
+                    .collect::<Vec<String>>()
+                    .join(" and ");
+
+                let robot_name = self.robot_name(build.robot);
+
+                let article = if robot_name.starts_with(['a', 'e', 'i', 'o', 'u']) {
+                    "an"
                 } else {
-                    // Simply waiting and taking no action is always an option if we can't build a
-                    // geode robot
-                    next_production_states.insert(ProductionState {
-                        robots: production_state.robots,
-                        resources: production_state.resources + production_state.robots,
-                    });
+                    "a"
+                };
+
+                writeln!(
+                    f,
+                    "Spend {} to start building {} {} robot.",
+                    cost, article, robot_name
+                )?;
+            }
+
+            for resource in 0..self.resource_names.len() {
+                let robots = summary.robots.0[resource];
+
+                if robots == 0 {
+                    continue;
                 }
+
+                let verb = if self.resource_names[resource] == "geode" {
+                    "crack"
+                } else {
+                    "collect"
+                };
+
+                writeln!(
+                    f,
+                    "{} {} {} {}{} {}; you now have {}.",
+                    robots,
+                    self.robot_name(resource),
+                    if robots == 1 { "robot" } else { "robots" },
+                    verb,
+                    if robots == 1 { "s" } else { "" },
+                    quantity(robots, resource, false),
+                    quantity(summary.resources.0[resource], resource, true),
+                )?;
             }
 
-            production_states = next_production_states;
+            if let Some(build) = summary.build {
+                writeln!(
+                    f,
+                    "The new {} robot is ready; you now have {} of them.",
+                    self.robot_name(build.robot),
+                    summary.robots.0[build.robot] + 1
+                )?;
+            }
         }
 
-        production_states
-            .iter()
-            .map(|production_state| production_state.resources.geodes)
-            .max()
-            .unwrap()
+        Ok(())
     }
 }
 
 impl FromStr for Blueprint {
     type Err = Box<dyn Error>;
 
+    // Blueprints may either be written as sentences like the puzzle input, or as a table with a
+    // header row of resource names followed by one row per robot with its cost in each resource:
+    //
+    //       ore clay
+    // ore     4    0
+    // clay    2    0
     fn from_str(string: &str) -> Result<Self, Self::Err> {
         lazy_static! {
-            static ref BLUEPRINT_PATTERN: Regex = Regex::new(
-                r"Blueprint \d+: Each ore robot costs (\d+) ore. Each clay robot costs (\d+) ore. Each obsidian robot costs (\d+) ore and (\d+) clay. Each geode robot costs (\d+) ore and (\d+) obsidian."
-            )
-            .unwrap();
+            static ref BLUEPRINT_PATTERN: Regex = Regex::new(r"^Blueprint \d+:").unwrap();
+            static ref ROBOT_PATTERN: Regex =
+                Regex::new(r"Each (\w+) robot costs ([^.]+)\.").unwrap();
+            static ref COST_PATTERN: Regex = Regex::new(r"^(\d+) (\w+)$").unwrap();
         }
 
-        if let Some(captures) = BLUEPRINT_PATTERN.captures(string) {
-            Ok(Blueprint {
-                ore_robot_cost: Resources {
-                    ore: captures[1].parse()?,
-                    clay: 0,
-                    obsidian: 0,
-                    geodes: 0,
-                },
-                clay_robot_cost: Resources {
-                    ore: captures[2].parse()?,
-                    clay: 0,
-                    obsidian: 0,
-                    geodes: 0,
-                },
-                obsidian_robot_cost: Resources {
-                    ore: captures[3].parse()?,
-                    clay: captures[4].parse()?,
-                    obsidian: 0,
-                    geodes: 0,
-                },
-                geode_robot_cost: Resources {
-                    ore: captures[5].parse()?,
-                    clay: 0,
-                    obsidian: captures[6].parse()?,
-                    geodes: 0,
-                },
-            })
+        let mut blueprint = Blueprint {
+            resource_names: vec![],
+            robot_costs: vec![],
+        };
+
+        if BLUEPRINT_PATTERN.is_match(string.trim()) {
+            let robots: Vec<(&str, &str)> = ROBOT_PATTERN
+                .captures_iter(string)
+                .map(|captures| {
+                    (
+                        captures.get(1).unwrap().as_str(),
+                        captures.get(2).unwrap().as_str(),
+                    )
+                })
+                .collect();
+
+            blueprint.resource_names = robots.iter().map(|(name, _)| name.to_string()).collect();
+            blueprint.validate_resource_names()?;
+
+            for (_, costs) in robots {
+                let mut cost = Resources::default();
+
+                for term in costs.split(" and ") {
+                    let captures = COST_PATTERN
+                        .captures(term.trim())
+                        .ok_or("Could not parse robot cost")?;
+
+                    let resource = blueprint
+                        .resource(&captures[2])
+                        .ok_or("Cost refers to a resource with no robot")?;
+
+                    cost.0[resource] = captures[1].parse()?;
+                }
+
+                blueprint.robot_costs.push(cost);
+            }
+        } else {
+            let mut lines = string.lines().filter(|line| !line.trim().is_empty());
+
+            blueprint.resource_names = lines
+                .next()
+                .ok_or("Blueprint table must have a header row")?
+                .split_whitespace()
+                .map(String::from)
+                .collect();
+
+            blueprint.validate_resource_names()?;
+
+            let mut robot_costs = vec![None; blueprint.resource_names.len()];
+
+            for line in lines {
+                let mut cells = line.split_whitespace();
+
+                let robot = cells
+                    .next()
+                    .and_then(|name| blueprint.resource(name))
+                    .ok_or("Table row does not name a known resource")?;
+
+                let amounts: Vec<u16> = cells.map(|cell| cell.parse()).collect::<Result<_, _>>()?;
+
+                if amounts.len() != blueprint.resource_names.len() {
+                    return Err("Table row has the wrong number of columns".into());
+                }
+
+                if robot_costs[robot]
+                    .replace(Resources::new(&amounts))
+                    .is_some()
+                {
+                    return Err("Table has more than one row for the same robot".into());
+                }
+            }
+
+            blueprint.robot_costs = robot_costs
+                .into_iter()
+                .collect::<Option<_>>()
+                .ok_or("Every resource must have a robot")?;
+        }
+
+        Ok(blueprint)
+    }
+}
+
+impl Blueprint {
+    fn validate_resource_names(&self) -> Result<(), Box<dyn Error>> {
+        if self.resource_names.is_empty() {
+            Err("Blueprint must have at least one robot".into())
+        } else if self.resource_names.len() > MAX_RESOURCES {
+            Err("Blueprint has too many kinds of resources".into())
+        } else if (1..self.resource_names.len())
+            .any(|i| self.resource_names[..i].contains(&self.resource_names[i]))
+        {
+            Err("Blueprint names the same resource more than once".into())
         } else {
-            Err("Could not parse blueprint".into())
+            Ok(())
         }
     }
 }
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
-struct Resources {
-    ore: u16,
-    clay: u16,
-    obsidian: u16,
-    geodes: u16,
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash)]
+struct Resources([u16; MAX_RESOURCES]);
+
+impl Resources {
+    fn new(amounts: &[u16]) -> Self {
+        let mut resources = Resources::default();
+        resources.0[..amounts.len()].copy_from_slice(amounts);
+
+        resources
+    }
 }
 
 impl PartialOrd for Resources {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        if self.ore == other.ore
-            && self.clay == other.clay
-            && self.obsidian == other.obsidian
-            && self.geodes == other.geodes
-        {
+        if self.0 == other.0 {
             Some(Ordering::Equal)
-        } else if self.ore <= other.ore
-            && self.clay <= other.clay
-            && self.obsidian <= other.obsidian
-            && self.geodes <= other.geodes
-        {
+        } else if self.0.iter().zip(other.0.iter()).all(|(a, b)| a <= b) {
             Some(Ordering::Less)
         } else {
             Some(Ordering::Greater)
@@ -287,31 +628,7 @@ impl Add for Resources {
     type Output = Resources;
 
     fn add(self, addend: Self) -> Self::Output {
-        Resources {
-            ore: if self.ore == u16::MAX {
-                u16::MAX
-            } else {
-                self.ore + addend.ore
-            },
-
-            clay: if self.clay == u16::MAX {
-                u16::MAX
-            } else {
-                self.clay + addend.clay
-            },
-
-            obsidian: if self.obsidian == u16::MAX {
-                u16::MAX
-            } else {
-                self.obsidian + addend.obsidian
-            },
-
-            geodes: if self.geodes == u16::MAX {
-                u16::MAX
-            } else {
-                self.geodes + addend.geodes
-            },
-        }
+        Resources(std::array::from_fn(|i| self.0[i] + addend.0[i]))
     }
 }
 
@@ -319,31 +636,15 @@ impl Sub for Resources {
     type Output = Resources;
 
     fn sub(self, subtrahend: Self) -> Self::Output {
-        Resources {
-            ore: if self.ore == u16::MAX {
-                u16::MAX
-            } else {
-                self.ore - subtrahend.ore
-            },
-
-            clay: if self.clay == u16::MAX {
-                u16::MAX
-            } else {
-                self.clay - subtrahend.clay
-            },
+        Resources(std::array::from_fn(|i| self.0[i] - subtrahend.0[i]))
+    }
+}
 
-            obsidian: if self.obsidian == u16::MAX {
-                u16::MAX
-            } else {
-                self.obsidian - subtrahend.obsidian
-            },
+impl Mul<u16> for Resources {
+    type Output = Resources;
 
-            geodes: if self.geodes == u16::MAX {
-                u16::MAX
-            } else {
-                self.geodes - subtrahend.geodes
-            },
-        }
+    fn mul(self, factor: u16) -> Self::Output {
+        Resources(self.0.map(|amount| amount * factor))
     }
 }
 
@@ -358,22 +659,32 @@ struct ProductionState {
     resources: Resources,
 }
 
+impl ProductionState {
+    // Returns the number of minutes we'd need to wait before we could afford something with the
+    // given cost, or `None` if we'll never be able to afford it with our current robots
+    fn time_to_afford(&self, cost: &Resources) -> Option<u16> {
+        cost.0
+            .iter()
+            .zip(self.resources.0.iter().zip(self.robots.0.iter()))
+            .map(|(&cost, (&available, &robots))| {
+                if available >= cost {
+                    Some(0)
+                } else if robots == 0 {
+                    None
+                } else {
+                    Some((cost - available).div_ceil(robots))
+                }
+            })
+            .try_fold(0, |max_wait, wait| wait.map(|wait| max_wait.max(wait)))
+    }
+}
+
 impl Default for ProductionState {
+    // We always start with a single robot that collects the first resource
     fn default() -> Self {
         Self {
-            robots: Resources {
-                ore: 1,
-                clay: 0,
-                obsidian: 0,
-                geodes: 0,
-            },
-
-            resources: Resources {
-                ore: 0,
-                clay: 0,
-                obsidian: 0,
-                geodes: 0,
-            },
+            robots: Resources::new(&[1]),
+            resources: Resources::default(),
         }
     }
 }
@@ -403,10 +714,160 @@ mod test {
         assert_eq!(62, blueprints[1].optimize_geodes(32));
     }
 
+    #[test]
+    fn test_optimal_plan() {
+        let blueprint = Blueprint::from_str(TEST_BLUEPRINTS.lines().next().unwrap()).unwrap();
+        let plan = blueprint.optimal_plan(blueprint.default_target(), 24);
+
+        assert_eq!(9, plan.amount);
+        assert_eq!(
+            vec![3, 5, 7, 11, 12, 15, 18, 21],
+            plan.builds
+                .iter()
+                .map(|build| build.minute)
+                .collect::<Vec<u16>>()
+        );
+
+        let minutes = plan.minutes();
+        assert_eq!(24, minutes.len());
+        assert_eq!(Resources::new(&[6, 41, 8, 9]), minutes[23].resources);
+
+        let narrative = plan.to_string();
+
+        assert!(narrative.starts_with(indoc! {"
+            == Minute 1 ==
+            1 ore-collecting robot collects 1 ore; you now have 1 ore.
+
+            == Minute 2 ==
+            1 ore-collecting robot collects 1 ore; you now have 2 ore.
+
+            == Minute 3 ==
+            Spend 2 ore to start building a clay-collecting robot.
+            1 ore-collecting robot collects 1 ore; you now have 1 ore.
+            The new clay-collecting robot is ready; you now have 1 of them.
+        "}));
+
+        assert!(narrative.ends_with(indoc! {"
+            == Minute 24 ==
+            1 ore-collecting robot collects 1 ore; you now have 6 ore.
+            4 clay-collecting robots collect 4 clay; you now have 41 clay.
+            2 obsidian-collecting robots collect 2 obsidian; you now have 8 obsidian.
+            2 geode-cracking robots crack 2 geodes; you now have 9 open geodes.
+        "}));
+    }
+
+    #[test]
+    fn test_blueprint_from_table() {
+        let table = Blueprint::from_str(indoc! {"
+                     ore clay obsidian geode
+            clay       2    0        0     0
+            ore        4    0        0     0
+            obsidian   3   14        0     0
+            geode      2    0        7     0
+        "})
+        .unwrap();
+
+        let sentences = Blueprint::from_str(TEST_BLUEPRINTS.lines().next().unwrap()).unwrap();
+
+        assert_eq!(sentences.resource_names, table.resource_names);
+        assert_eq!(sentences.robot_costs, table.robot_costs);
+
+        assert!(Blueprint::from_str("ore clay\nore 1 0").is_err());
+        assert!(Blueprint::from_str("ore clay\nore 1\nclay 1 0").is_err());
+        assert!(Blueprint::from_str("Blueprint 1: Each ore robot costs 2 clay.").is_err());
+    }
+
+    #[test]
+    fn test_resource_chains() {
+        // A longer chain with a robot that costs more than one kind of resource
+        let blueprint = Blueprint::from_str(
+            "Blueprint 1: Each sand robot costs 2 sand. Each glass robot costs 3 sand. \
+            Each lens robot costs 2 sand and 6 glass. Each telescope robot costs 4 glass and 4 lens. \
+            Each star robot costs 3 sand, 0 glass and 5 telescopes.",
+        );
+
+        // Costs must be joined with "and"
+        assert!(blueprint.is_err());
+
+        let blueprint = Blueprint::from_str(
+            "Blueprint 1: Each sand robot costs 2 sand. Each glass robot costs 3 sand. \
+            Each lens robot costs 2 sand and 6 glass. Each telescope robot costs 4 glass and 4 lens. \
+            Each star robot costs 3 sand and 5 telescopes.",
+        )
+        .unwrap();
+
+        assert_eq!(
+            vec!["sand", "glass", "lens", "telescope", "star"],
+            blueprint.resource_names
+        );
+
+        assert_eq!(Resources::new(&[3, 0, 0, 5, 0]), blueprint.robot_costs[4]);
+
+        assert_eq!(1, blueprint.optimal_plan(4, 20).amount);
+        assert_eq!(41, blueprint.optimal_plan(2, 20).amount);
+        assert_eq!(24, blueprint.optimal_plan(4, 26).amount);
+        assert_eq!(110, blueprint.optimal_plan(2, 26).amount);
+    }
+
+    #[test]
+    fn test_time_to_afford() {
+        let production_state = ProductionState {
+            robots: Resources::new(&[2, 1, 0, 0]),
+            resources: Resources::new(&[1, 4, 0, 0]),
+        };
+
+        let cost = |ore, clay, obsidian| Resources::new(&[ore, clay, obsidian]);
+
+        assert_eq!(Some(0), production_state.time_to_afford(&cost(1, 4, 0)));
+        assert_eq!(Some(2), production_state.time_to_afford(&cost(4, 0, 0)));
+        assert_eq!(Some(3), production_state.time_to_afford(&cost(2, 7, 0)));
+        assert_eq!(None, production_state.time_to_afford(&cost(1, 0, 1)));
+    }
+
     #[test]
     fn test_quality_level_sum() {
         let factory = RobotFactory::from_str(TEST_BLUEPRINTS).unwrap();
 
-        assert_eq!(33, factory.quality_level_sum(24));
+        assert_eq!(
+            33,
+            RobotFactory::quality_level_sum(&factory.evaluate(2, 24, 1))
+        );
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let factory = RobotFactory::from_str(&TEST_BLUEPRINTS.repeat(3)).unwrap();
+
+        let sequential = factory.evaluate(6, 24, 1);
+        let parallel = factory.evaluate(6, 24, 4);
+
+        assert_eq!(
+            vec![1, 2, 3, 4, 5, 6],
+            parallel
+                .iter()
+                .map(|evaluation| evaluation.blueprint)
+                .collect::<Vec<usize>>()
+        );
+
+        assert_eq!(
+            vec![9, 12, 9, 12, 9, 12],
+            parallel
+                .iter()
+                .map(|evaluation| evaluation.geodes)
+                .collect::<Vec<u16>>()
+        );
+
+        assert_eq!(
+            RobotFactory::quality_level_sum(&sequential),
+            RobotFactory::quality_level_sum(&parallel)
+        );
+
+        assert_eq!(
+            56 * 62,
+            RobotFactory::geode_product(&factory.evaluate(2, 32, 3))
+        );
+
+        // Asking for more blueprints than we have is fine
+        assert_eq!(6, factory.evaluate(100, 1, 8).len());
     }
 }
@@ -12,16 +12,16 @@ fn main() -> Result<(), Box<dyn Error>> {
             .map(|line| line.parse())
             .collect::<Result<_, _>>()?;
 
-        let mut gps = GrovePositioningSystem::try_from(values.as_slice())?;
+        let gps = GrovePositioningSystem::try_from(values.as_slice())?;
 
         println!(
             "Coordinate sum with key = 1, mixing rounds = 1: {}",
-            gps.coordinate_sum(1, 1)
+            gps.coordinate_sum(1, 1)?
         );
 
         println!(
             "Coordinate sum with key = 811589153, mixing rounds = 10: {}",
-            gps.coordinate_sum(811589153, 10)
+            gps.coordinate_sum(811589153, 10)?
         );
 
         Ok(())
@@ -31,242 +31,330 @@ fn main() -> Result<(), Box<dyn Error>> {
 }
 
 struct GrovePositioningSystem {
-    ring: *mut RingNode,
-    len: usize,
+    values: Vec<i64>,
 }
 
 impl GrovePositioningSystem {
-    fn ring(&self) -> &RingNode {
-        unsafe { &*(self.ring) }
-    }
+    fn coordinate_sum(
+        &self,
+        decryption_key: i64,
+        mixing_rounds: usize,
+    ) -> Result<i64, Box<dyn Error>> {
+        let mut mixing_list = MixingList::new(&self.values, decryption_key);
 
-    unsafe fn mix(&mut self, decryption_key: i64) {
-        let mut node = self.ring;
+        for _ in 0..mixing_rounds {
+            mixing_list.mix();
+        }
 
-        loop {
-            // `len - 1` in this case because the list no longer contains `node`
-            let move_distance = ((*node).value * decryption_key) % (self.len - 1) as i64;
+        let mixed = mixing_list.values();
 
-            if move_distance != 0 {
-                // Remove `node` from its current position
-                (*(*node).mixed_prev).mixed_next = (*node).mixed_next;
-                (*(*node).mixed_next).mixed_prev = (*node).mixed_prev;
+        let zero = mixed
+            .iter()
+            .position(|&value| value == 0)
+            .ok_or("Values must contain a zero")?;
 
-                let (left, right) = if move_distance > 0 {
-                    let mut left = node;
+        Ok([1000, 2000, 3000]
+            .iter()
+            .map(|offset| mixed[(zero + offset) % mixed.len()])
+            .sum())
+    }
+}
 
-                    for _ in 0..move_distance {
-                        left = (*left).mixed_next;
-                    }
+impl TryFrom<&[i64]> for GrovePositioningSystem {
+    type Error = Box<dyn Error>;
 
-                    (left, (*left).mixed_next)
-                } else {
-                    let mut right = node;
+    fn try_from(values: &[i64]) -> Result<Self, Self::Error> {
+        if values.is_empty() {
+            Err("Values must not be empty".into())
+        } else {
+            Ok(GrovePositioningSystem {
+                values: Vec::from(values),
+            })
+        }
+    }
+}
 
-                    for _ in 0..move_distance.abs() {
-                        right = (*right).mixed_prev;
-                    }
+// A circular list of values stored as an implicit treap (a randomly-balanced binary tree ordered by
+// position rather than by key). Nodes live in a single vector in their original order and refer to
+// one another by index, so we can find any value's current position by walking up from its node,
+// then move it in O(log n) time by splitting and re-merging the tree.
+struct MixingList {
+    nodes: Vec<Node>,
+    root: Option<usize>,
+}
 
-                    ((*right).mixed_prev, right)
-                };
+struct Node {
+    value: i64,
+    priority: u64,
 
-                // Add `node` to its new position
-                (*left).mixed_next = node;
-                (*right).mixed_prev = node;
-                (*node).mixed_prev = left;
-                (*node).mixed_next = right;
-            }
+    // The number of nodes in the subtree rooted at this node
+    size: usize,
 
-            node = (*node).original_next;
+    left: Option<usize>,
+    right: Option<usize>,
+    parent: Option<usize>,
+}
 
-            if std::ptr::eq(node, self.ring) {
-                break;
-            }
+impl MixingList {
+    fn new(values: &[i64], decryption_key: i64) -> Self {
+        let mut mixing_list = MixingList {
+            nodes: values
+                .iter()
+                .enumerate()
+                .map(|(i, &value)| Node {
+                    value: value * decryption_key,
+                    priority: MixingList::priority(i),
+                    size: 1,
+                    left: None,
+                    right: None,
+                    parent: None,
+                })
+                .collect(),
+            root: None,
+        };
+
+        for i in 0..values.len() {
+            mixing_list.root = mixing_list.merge(mixing_list.root, Some(i));
         }
-    }
 
-    fn reset(&mut self) {
-        let mut node = self.ring;
+        mixing_list
+    }
 
-        loop {
-            unsafe {
-                (*node).mixed_next = (*node).original_next;
-                (*node).mixed_prev = (*node).original_prev;
+    // Treaps need random priorities to stay balanced, but there's no need for them to differ from
+    // one run to the next; this is the SplitMix64 finalizer
+    fn priority(index: usize) -> u64 {
+        let mut z = (index as u64)
+            .wrapping_add(1)
+            .wrapping_mul(0x9e3779b97f4a7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
 
-                node = (*node).original_next;
-            }
+        z ^ (z >> 31)
+    }
 
-            if std::ptr::eq(node, self.ring) {
-                break;
-            }
-        }
+    fn len(&self) -> usize {
+        self.nodes.len()
     }
 
-    fn coordinate_sum(&mut self, decryption_key: i64, mixing_rounds: usize) -> i64 {
-        self.reset();
+    fn mix(&mut self) {
+        // Moving a value all the way around the list (minus itself) leaves it where it started
+        let cycle_len = self.len() as i64 - 1;
 
-        for _ in 0..mixing_rounds {
-            unsafe {
-                self.mix(decryption_key);
-            }
+        if cycle_len == 0 {
+            return;
         }
 
-        let mut node = {
-            let mut node = self.ring();
-
-            loop {
-                if node.value == 0 {
-                    break node;
-                }
+        for i in 0..self.len() {
+            let position = self.position(i);
 
-                node = node.original_next();
-            }
-        };
+            let (before, rest) = self.split(self.root, position);
+            let (_, after) = self.split(rest, 1);
+            let remaining = self.merge(before, after);
 
-        let mut sum = 0;
+            let destination =
+                (position as i64 + self.nodes[i].value).rem_euclid(cycle_len) as usize;
 
-        for _ in 0..3 {
-            for _ in 0..1000 {
-                node = node.mixed_next();
-            }
+            let (before, after) = self.split(remaining, destination);
+            let before = self.merge(before, Some(i));
 
-            sum += node.value * decryption_key;
+            self.root = self.merge(before, after);
         }
-
-        sum
     }
-}
 
-impl TryFrom<&[i64]> for GrovePositioningSystem {
-    type Error = Box<dyn Error>;
+    // Returns the current values of the list, starting from an arbitrary point in the cycle
+    fn values(&self) -> Vec<i64> {
+        let mut values = Vec::with_capacity(self.len());
+        let mut stack = vec![];
+        let mut node = self.root;
 
-    fn try_from(values: &[i64]) -> Result<Self, Self::Error> {
-        if values.is_empty() {
-            Err("Values must not be empty".into())
-        } else {
-            let mut head = Box::into_raw(Box::new(RingNode::new(values[0])));
-
-            unsafe {
-                (*head).original_next = head;
-                (*head).original_prev = head;
-                (*head).mixed_next = head;
-                (*head).mixed_prev = head;
+        while node.is_some() || !stack.is_empty() {
+            while let Some(i) = node {
+                stack.push(i);
+                node = self.nodes[i].left;
             }
 
-            for i in &values[1..] {
-                let tail = unsafe { (*head).original_prev };
-
-                let new_tail = Box::into_raw(Box::new(RingNode {
-                    original_next: head,
-                    original_prev: tail,
-                    mixed_next: head,
-                    mixed_prev: tail,
-                    value: *i,
-                }));
-
-                unsafe {
-                    (*head).original_prev = new_tail;
-                    (*head).mixed_prev = new_tail;
-                    (*tail).original_next = new_tail;
-                    (*tail).mixed_next = new_tail;
-                }
+            if let Some(i) = stack.pop() {
+                values.push(self.nodes[i].value);
+                node = self.nodes[i].right;
             }
+        }
+
+        values
+    }
+
+    // Returns the current position (in the list's in-order traversal) of the value that started at
+    // the given index
+    fn position(&self, index: usize) -> usize {
+        let mut position = self.size(self.nodes[index].left);
+        let mut node = index;
 
-            let gps = Self {
-                ring: head,
-                len: values.len(),
-            };
+        while let Some(parent) = self.nodes[node].parent {
+            if self.nodes[parent].right == Some(node) {
+                position += self.size(self.nodes[parent].left) + 1;
+            }
 
-            Ok(gps)
+            node = parent;
         }
+
+        position
     }
-}
 
-impl RingNode {
-    fn new(value: i64) -> Self {
-        RingNode {
-            original_next: std::ptr::null_mut(),
-            original_prev: std::ptr::null_mut(),
+    fn size(&self, node: Option<usize>) -> usize {
+        node.map(|i| self.nodes[i].size).unwrap_or(0)
+    }
 
-            mixed_next: std::ptr::null_mut(),
-            mixed_prev: std::ptr::null_mut(),
+    fn update(&mut self, node: usize) {
+        self.nodes[node].size =
+            self.size(self.nodes[node].left) + self.size(self.nodes[node].right) + 1;
 
-            value,
+        for child in [self.nodes[node].left, self.nodes[node].right]
+            .into_iter()
+            .flatten()
+        {
+            self.nodes[child].parent = Some(node);
         }
     }
-}
 
-struct RingNode {
-    original_next: *mut RingNode,
-    original_prev: *mut RingNode,
+    // Splits the given tree into two trees, the first of which contains the first `count` values
+    fn split(&mut self, node: Option<usize>, count: usize) -> (Option<usize>, Option<usize>) {
+        if let Some(i) = node {
+            self.nodes[i].parent = None;
 
-    mixed_next: *mut RingNode,
-    mixed_prev: *mut RingNode,
+            let left_size = self.size(self.nodes[i].left);
 
-    value: i64,
-}
+            if count <= left_size {
+                let (left, right) = self.split(self.nodes[i].left, count);
+                self.nodes[i].left = right;
+                self.update(i);
+
+                (left, Some(i))
+            } else {
+                let (left, right) = self.split(self.nodes[i].right, count - left_size - 1);
+                self.nodes[i].right = left;
+                self.update(i);
 
-impl RingNode {
-    fn original_next(&self) -> &RingNode {
-        unsafe { &*(self.original_next) }
+                (Some(i), right)
+            }
+        } else {
+            (None, None)
+        }
     }
 
-    fn mixed_next(&self) -> &RingNode {
-        unsafe { &*(self.mixed_next) }
+    // Joins two trees, placing all of the values in `left` before all of the values in `right`
+    fn merge(&mut self, left: Option<usize>, right: Option<usize>) -> Option<usize> {
+        match (left, right) {
+            (None, right) => right,
+            (left, None) => left,
+            (Some(l), Some(r)) => {
+                let root = if self.nodes[l].priority > self.nodes[r].priority {
+                    self.nodes[l].right = self.merge(self.nodes[l].right, Some(r));
+                    self.update(l);
+
+                    l
+                } else {
+                    self.nodes[r].left = self.merge(Some(l), self.nodes[r].left);
+                    self.update(r);
+
+                    r
+                };
+
+                self.nodes[root].parent = None;
+                Some(root)
+            }
+        }
     }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use advent_of_code_2022::xorshift::XorShift;
 
     const TEST_NUMBERS: [i64; 7] = [1, 2, -3, 3, -2, 0, 4];
 
-    #[test]
-    fn test_mix() {
-        let mut gps = GrovePositioningSystem::try_from(TEST_NUMBERS.as_slice()).unwrap();
+    // Returns the values of the list, rotated so the value that started first is first
+    fn mixed_values(mixing_list: &MixingList) -> Vec<i64> {
+        let mut values = mixing_list.values();
+        values.rotate_left(mixing_list.position(0));
 
-        unsafe {
-            gps.mix(1);
-        }
+        values
+    }
 
-        {
-            let mut ring = gps.ring();
-            let mut values = vec![];
+    // Mixes values by literally removing and re-inserting them in a vector
+    fn naive_mix(values: &[i64], decryption_key: i64, mixing_rounds: usize) -> Vec<i64> {
+        let mut mixed: Vec<(usize, i64)> = values
+            .iter()
+            .map(|value| value * decryption_key)
+            .enumerate()
+            .collect();
+
+        let cycle_len = values.len() as i64 - 1;
 
-            loop {
-                values.push(ring.value);
-                ring = ring.original_next();
+        for _ in 0..mixing_rounds {
+            for i in 0..values.len() {
+                let position = mixed.iter().position(|&(j, _)| i == j).unwrap();
+                let entry = mixed.remove(position);
+
+                let destination = if cycle_len == 0 {
+                    0
+                } else {
+                    (position as i64 + entry.1).rem_euclid(cycle_len) as usize
+                };
 
-                if std::ptr::eq(ring, gps.ring()) {
-                    break;
-                }
+                mixed.insert(destination, entry);
             }
+        }
 
-            assert_eq!(Vec::from(TEST_NUMBERS), values);
-        };
+        let first = mixed.iter().position(|&(j, _)| j == 0).unwrap();
+        mixed.rotate_left(first);
 
-        {
-            let mut ring = gps.ring();
-            let mut values = vec![];
+        mixed.into_iter().map(|(_, value)| value).collect()
+    }
 
-            loop {
-                values.push(ring.value);
-                ring = ring.mixed_next();
+    #[test]
+    fn test_mix() {
+        let mut mixing_list = MixingList::new(&TEST_NUMBERS, 1);
+        mixing_list.mix();
+
+        assert_eq!(vec![1, 2, -3, 4, 0, 3, -2], mixed_values(&mixing_list));
+    }
+
+    #[test]
+    fn test_mix_matches_naive_mix() {
+        let mut random = XorShift::new(0x2545f4914f6cdd1d);
+
+        for _ in 0..200 {
+            let len = random.below(40) as usize + 1;
+            let spread = [3, 50, 10_000][random.below(3) as usize];
 
-                if std::ptr::eq(ring, gps.ring()) {
-                    break;
-                }
+            let values: Vec<i64> = (0..len)
+                .map(|_| random.below(2 * spread + 1) as i64 - spread as i64)
+                .collect();
+
+            let decryption_key = [1, 811589153][random.below(2) as usize];
+            let mixing_rounds = random.below(3) as usize + 1;
+
+            let mut mixing_list = MixingList::new(&values, decryption_key);
+
+            for _ in 0..mixing_rounds {
+                mixing_list.mix();
             }
 
-            assert_eq!(vec![1, 2, -3, 4, 0, 3, -2], values);
-        };
+            assert_eq!(
+                naive_mix(&values, decryption_key, mixing_rounds),
+                mixed_values(&mixing_list),
+                "Mixing {:?} with key {} for {} rounds",
+                values,
+                decryption_key,
+                mixing_rounds
+            );
+        }
     }
 
     #[test]
     fn test_coordinate_sum() {
-        let mut gps = GrovePositioningSystem::try_from(TEST_NUMBERS.as_slice()).unwrap();
-        assert_eq!(3, gps.coordinate_sum(1, 1));
-        assert_eq!(1623178306, gps.coordinate_sum(811589153, 10));
+        let gps = GrovePositioningSystem::try_from(TEST_NUMBERS.as_slice()).unwrap();
+        assert_eq!(3, gps.coordinate_sum(1, 1).unwrap());
+        assert_eq!(1623178306, gps.coordinate_sum(811589153, 10).unwrap());
     }
 }
@@ -1,5 +1,6 @@
 use std::collections::HashMap;
 use std::error::Error;
+use std::fmt::{Display, Formatter};
 use std::fs;
 use std::str::FromStr;
 
@@ -7,121 +8,548 @@ fn main() -> Result<(), Box<dyn Error>> {
     let args: Vec<String> = std::env::args().collect();
 
     if let Some(path) = args.get(1) {
-        let monkeys = YellingMonkeys::from_str(fs::read_to_string(path)?.as_str())?;
+        let mut monkeys = YellingMonkeys::from_str(fs::read_to_string(path)?.as_str())?;
 
-        println!("Monkey named 'root' yells: {}", monkeys.eval("root"));
-        println!("Human should yell: {}", monkeys.find_human_number());
+        if let Some(root) = args.get(3) {
+            monkeys.root = root.clone();
+        }
+
+        if let Some(human) = args.get(4) {
+            monkeys.human = human.clone();
+        }
+
+        println!(
+            "Monkey named '{}' yells: {}",
+            monkeys.root,
+            monkeys.eval(&monkeys.root)?
+        );
+        println!("Human should yell: {}", monkeys.find_human_number()?);
+
+        let (left, right) = monkeys.equation()?;
+        println!("Root checks that {} = {}", left, right);
+
+        if let Some(dot_path) = args.get(2).filter(|path| !path.is_empty()) {
+            fs::write(dot_path, monkeys.expression(&monkeys.root)?.to_dot())?;
+        }
 
         Ok(())
     } else {
-        Err("Usage: day21 INPUT_FILE_PATH".into())
+        Err("Usage: day21 INPUT_FILE_PATH [DOT_OUTPUT_PATH [ROOT_NAME [HUMAN_NAME]]]".into())
     }
 }
 
 struct YellingMonkeys {
-    monkeys: HashMap<String, Monkey>,
+    names: Vec<String>,
+    indices: HashMap<String, usize>,
+    monkeys: Vec<Monkey>,
+
+    // Every monkey, ordered so that each one comes after all of the monkeys it listens to
+    order: Vec<usize>,
+
+    root: String,
+    human: String,
 }
 
 impl YellingMonkeys {
-    fn eval(&self, monkey_name: &str) -> f64 {
-        match self.monkeys.get(monkey_name).unwrap() {
-            Monkey::Literal(literal) => *literal,
-            Monkey::Add(a, b) => self.eval(a) + self.eval(b),
-            Monkey::Subtract(a, b) => self.eval(a) - self.eval(b),
-            Monkey::Multiply(a, b) => self.eval(a) * self.eval(b),
-            Monkey::Divide(a, b) => self.eval(a) / self.eval(b),
-        }
+    fn index(&self, monkey_name: &str) -> Result<usize, Box<dyn Error>> {
+        self.indices
+            .get(monkey_name)
+            .copied()
+            .ok_or_else(|| format!("No monkey named '{}'", monkey_name).into())
     }
 
-    fn find_human_number(mut self) -> f64 {
-        const ROOT: &str = "root";
+    // Evaluates the given monkey and everything it depends on exactly once, visiting monkeys in
+    // topological order so every operand has a value before any monkey needs it
+    fn evaluate<T: Clone>(
+        &self,
+        monkey_name: &str,
+        mut evaluate_monkey: impl FnMut(usize, &Monkey, &[Option<T>]) -> Result<T, Box<dyn Error>>,
+    ) -> Result<T, Box<dyn Error>> {
+        let target = self.index(monkey_name)?;
 
-        let monkeys = match self.monkeys.get(ROOT).unwrap() {
-            Monkey::Literal(_) => panic!("Root has a literal value"),
-            Monkey::Add(a, b) => (a.clone(), b.clone()),
-            Monkey::Subtract(a, b) => (a.clone(), b.clone()),
-            Monkey::Multiply(a, b) => (a.clone(), b.clone()),
-            Monkey::Divide(a, b) => (a.clone(), b.clone()),
-        };
+        let mut needed = vec![false; self.monkeys.len()];
+        let mut exploration_stack = vec![target];
 
-        let mut left = 0f64;
-        let mut right = {
-            let delta_left = self.delta(left, &monkeys);
-            let mut right = 1f64;
+        while let Some(monkey) = exploration_stack.pop() {
+            if !needed[monkey] {
+                needed[monkey] = true;
 
-            while self.delta(right, &monkeys).signum() == delta_left.signum() {
-                right *= 2f64;
+                if let Monkey::Operation(a, _, b) = self.monkeys[monkey] {
+                    exploration_stack.extend([a, b]);
+                }
             }
+        }
 
-            right
-        };
+        let mut values = vec![None; self.monkeys.len()];
 
-        loop {
-            let mid = (left + right) / 2f64;
+        for &monkey in self.order.iter().filter(|&&monkey| needed[monkey]) {
+            values[monkey] = Some(evaluate_monkey(monkey, &self.monkeys[monkey], &values)?);
+        }
 
-            let a = self.delta(left as f64, &monkeys);
-            let b = self.delta(right, &monkeys);
-            let c = self.delta(mid, &monkeys);
+        Ok(values[target]
+            .take()
+            .expect("Target should have been evaluated"))
+    }
 
-            if a == 0f64 {
-                return left;
-            } else if b == 0f64 {
-                return right;
-            } else if c == 0f64 {
-                return mid;
+    fn eval(&self, monkey_name: &str) -> Result<Rational, Box<dyn Error>> {
+        self.evaluate(monkey_name, |_, monkey, values| match monkey {
+            Monkey::Literal(literal) => Ok(Rational::from(*literal)),
+            Monkey::Operation(a, operator, b) => {
+                operator.apply(operand(values, *a), operand(values, *b))
             }
+        })
+    }
 
-            if a.signum() != c.signum() {
-                right = mid;
-            } else if b.signum() != c.signum() {
-                left = mid;
-            } else {
-                panic!("All values have same sign")
+    // Evaluates the given monkey as a linear function of whatever the human yells
+    fn eval_linear(&self, monkey_name: &str) -> Result<Linear, Box<dyn Error>> {
+        let human = self.index(&self.human)?;
+
+        self.evaluate(monkey_name, |index, monkey, values| {
+            if index == human {
+                return Ok(Linear {
+                    slope: Rational::ONE,
+                    intercept: Rational::ZERO,
+                });
             }
+
+            match monkey {
+                Monkey::Literal(literal) => Ok(Linear::constant(Rational::from(*literal))),
+                Monkey::Operation(a, operator, b) => {
+                    let (a, b) = (operand(values, *a), operand(values, *b));
+
+                    match operator {
+                        Operator::Add => a.checked_add(b),
+                        Operator::Subtract => a.checked_sub(b),
+                        Operator::Multiply if a.is_constant() => b.checked_mul(a.intercept),
+                        Operator::Multiply if b.is_constant() => a.checked_mul(b.intercept),
+                        Operator::Divide if b.is_constant() => {
+                            a.checked_mul(Rational::ONE.checked_div(b.intercept)?)
+                        }
+                        _ => Err("Equation is not linear in the human's number".into()),
+                    }
+                }
+            }
+        })
+    }
+
+    // The root monkey checks that its two operands are equal, which means their difference (a linear
+    // function of the human's number) must be zero
+    fn find_human_number(&self) -> Result<i128, Box<dyn Error>> {
+        let (a, b) = self.root_operands()?;
+
+        let difference = self.eval_linear(a)?.checked_sub(self.eval_linear(b)?)?;
+
+        if difference.is_constant() {
+            return if difference.intercept == Rational::ZERO {
+                Err("Every number satisfies the equation".into())
+            } else {
+                Err("No number satisfies the equation".into())
+            };
+        }
+
+        let solution = difference
+            .intercept
+            .checked_neg()?
+            .checked_div(difference.slope)?;
+
+        if solution.denominator == 1 {
+            Ok(solution.numerator)
+        } else {
+            Err(format!("Equation has no integer solution ({})", solution).into())
         }
     }
 
-    fn delta(&mut self, value: f64, monkeys: &(String, String)) -> f64 {
-        const HUMAN: &str = "humn";
+    fn root_operands(&self) -> Result<(&str, &str), Box<dyn Error>> {
+        match self.monkeys[self.index(&self.root)?] {
+            Monkey::Literal(_) => Err("Root has a literal value".into()),
+            Monkey::Operation(a, _, b) => Ok((&self.names[a], &self.names[b])),
+        }
+    }
+
+    // Builds the expression a monkey yells, folding away any part that doesn't depend on the human
+    fn expression(&self, monkey_name: &str) -> Result<Expression, Box<dyn Error>> {
+        let human = self.indices.get(&self.human).copied();
 
-        *self.monkeys.get_mut(HUMAN).unwrap() = Monkey::Literal(value);
-        self.eval(monkeys.0.as_str()) as f64 - self.eval(monkeys.1.as_str()) as f64
+        self.evaluate(monkey_name, |index, monkey, values| {
+            if Some(index) == human {
+                return Ok(Expression::Variable(self.human.clone()));
+            }
+
+            Ok(match monkey {
+                Monkey::Literal(literal) => Expression::Constant(Rational::from(*literal)),
+                Monkey::Operation(a, operator, b) => {
+                    match (operand(values, *a), operand(values, *b)) {
+                        (Expression::Constant(a), Expression::Constant(b)) => {
+                            Expression::Constant(operator.apply(a, b)?)
+                        }
+                        (a, b) => Expression::Operation(Box::new(a), *operator, Box::new(b)),
+                    }
+                }
+            })
+        })
     }
+
+    // Returns the two sides of the equation the root monkey checks
+    fn equation(&self) -> Result<(Expression, Expression), Box<dyn Error>> {
+        let (a, b) = self.root_operands()?;
+
+        Ok((self.expression(a)?, self.expression(b)?))
+    }
+}
+
+fn operand<T: Clone>(values: &[Option<T>], monkey: usize) -> T {
+    values[monkey]
+        .clone()
+        .expect("Operands should be evaluated before the monkeys that use them")
 }
 
 impl FromStr for YellingMonkeys {
     type Err = Box<dyn Error>;
 
     fn from_str(string: &str) -> Result<Self, Self::Err> {
-        let mut monkeys = HashMap::new();
+        let mut names = vec![];
+        let mut indices = HashMap::new();
+        let mut expressions = vec![];
 
         for line in string.lines() {
             if let [name, expression] = line.split(": ").collect::<Vec<&str>>().as_slice() {
-                let monkey = match expression.split(' ').collect::<Vec<&str>>().as_slice() {
-                    [literal] => Monkey::Literal(literal.parse()?),
-                    [a, "+", b] => Monkey::Add(a.to_string(), b.to_string()),
-                    [a, "-", b] => Monkey::Subtract(a.to_string(), b.to_string()),
-                    [a, "*", b] => Monkey::Multiply(a.to_string(), b.to_string()),
-                    [a, "/", b] => Monkey::Divide(a.to_string(), b.to_string()),
-                    _ => return Err("Could not parse monkey line".into()),
-                };
+                if indices.insert(name.to_string(), names.len()).is_some() {
+                    return Err(format!("Monkey '{}' is defined more than once", name).into());
+                }
 
-                monkeys.insert(name.to_string(), monkey);
+                names.push(name.to_string());
+                expressions.push(*expression);
             } else {
                 return Err("Could not parse monkey line".into());
             }
         }
 
-        Ok(YellingMonkeys { monkeys })
+        let index = |monkey_name: &str, operand: &str| {
+            indices.get(operand).copied().ok_or_else(|| {
+                format!(
+                    "Monkey '{}' listens to undefined monkey '{}'",
+                    monkey_name, operand
+                )
+            })
+        };
+
+        let monkeys: Vec<Monkey> = names
+            .iter()
+            .zip(expressions)
+            .map(|(name, expression)| {
+                let (a, operator, b) = match expression.split(' ').collect::<Vec<&str>>().as_slice()
+                {
+                    [literal] => return Ok(Monkey::Literal(literal.parse()?)),
+                    [a, "+", b] => (*a, Operator::Add, *b),
+                    [a, "-", b] => (*a, Operator::Subtract, *b),
+                    [a, "*", b] => (*a, Operator::Multiply, *b),
+                    [a, "/", b] => (*a, Operator::Divide, *b),
+                    _ => return Err("Could not parse monkey line".into()),
+                };
+
+                Ok(Monkey::Operation(
+                    index(name, a)?,
+                    operator,
+                    index(name, b)?,
+                ))
+            })
+            .collect::<Result<_, Box<dyn Error>>>()?;
+
+        // Kahn's algorithm: repeatedly take monkeys whose operands have all been placed already; if
+        // we run out before placing everybody, the remaining monkeys must form a cycle
+        let mut listeners = vec![vec![]; monkeys.len()];
+        let mut unplaced_operands = vec![0; monkeys.len()];
+
+        for (monkey, definition) in monkeys.iter().enumerate() {
+            if let Monkey::Operation(a, _, b) = definition {
+                for operand in [*a, *b] {
+                    listeners[operand].push(monkey);
+                    unplaced_operands[monkey] += 1;
+                }
+            }
+        }
+
+        let mut order: Vec<usize> = (0..monkeys.len())
+            .filter(|&monkey| unplaced_operands[monkey] == 0)
+            .collect();
+
+        let mut i = 0;
+
+        while let Some(&monkey) = order.get(i) {
+            for &listener in &listeners[monkey] {
+                unplaced_operands[listener] -= 1;
+
+                if unplaced_operands[listener] == 0 {
+                    order.push(listener);
+                }
+            }
+
+            i += 1;
+        }
+
+        if let Some(monkey) = (0..monkeys.len()).find(|&monkey| unplaced_operands[monkey] > 0) {
+            return Err(format!("Monkey '{}' depends on a cycle of monkeys", names[monkey]).into());
+        }
+
+        Ok(YellingMonkeys {
+            names,
+            indices,
+            monkeys,
+            order,
+            root: String::from("root"),
+            human: String::from("humn"),
+        })
     }
 }
 
 enum Monkey {
-    Literal(f64),
-    Add(String, String),
-    Subtract(String, String),
-    Multiply(String, String),
-    Divide(String, String),
+    Literal(i128),
+    Operation(usize, Operator, usize),
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Operator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+}
+
+impl Operator {
+    fn apply(&self, a: Rational, b: Rational) -> Result<Rational, Box<dyn Error>> {
+        match self {
+            Operator::Add => a.checked_add(b),
+            Operator::Subtract => a.checked_sub(b),
+            Operator::Multiply => a.checked_mul(b),
+            Operator::Divide => a.checked_div(b),
+        }
+    }
+
+    fn symbol(&self) -> char {
+        match self {
+            Operator::Add => '+',
+            Operator::Subtract => '-',
+            Operator::Multiply => '*',
+            Operator::Divide => '/',
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum Expression {
+    Constant(Rational),
+    Variable(String),
+    Operation(Box<Expression>, Operator, Box<Expression>),
+}
+
+impl Expression {
+    fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph expression {\n");
+        self.write_dot(&mut dot, &mut 0);
+        dot.push_str("}\n");
+
+        dot
+    }
+
+    // Writes this expression's node and everything below it, returning the ID of this node
+    fn write_dot(&self, dot: &mut String, next_id: &mut usize) -> usize {
+        let id = *next_id;
+        *next_id += 1;
+
+        match self {
+            Expression::Constant(value) => {
+                dot.push_str(&format!("    n{} [label=\"{}\", shape=box];\n", id, value));
+            }
+            Expression::Variable(name) => {
+                dot.push_str(&format!(
+                    "    n{} [label=\"{}\", shape=doublecircle];\n",
+                    id, name
+                ));
+            }
+            Expression::Operation(a, operator, b) => {
+                dot.push_str(&format!(
+                    "    n{} [label=\"{}\", shape=circle];\n",
+                    id,
+                    operator.symbol()
+                ));
+
+                for operand in [a, b] {
+                    let operand_id = operand.write_dot(dot, next_id);
+                    dot.push_str(&format!("    n{} -> n{};\n", id, operand_id));
+                }
+            }
+        }
+
+        id
+    }
+}
+
+impl Display for Expression {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Expression::Constant(value) => write!(f, "{}", value),
+            Expression::Variable(name) => write!(f, "{}", name),
+            Expression::Operation(a, operator, b) => {
+                write!(f, "({} {} {})", a, operator.symbol(), b)
+            }
+        }
+    }
+}
+
+const OVERFLOW: &str = "Arithmetic overflow";
+
+// An exact fraction, always stored in lowest terms with a positive denominator
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+struct Rational {
+    numerator: i128,
+    denominator: i128,
+}
+
+impl Rational {
+    const ZERO: Rational = Rational {
+        numerator: 0,
+        denominator: 1,
+    };
+
+    const ONE: Rational = Rational {
+        numerator: 1,
+        denominator: 1,
+    };
+
+    fn new(numerator: i128, denominator: i128) -> Self {
+        assert_ne!(0, denominator, "Denominator must not be zero");
+
+        let gcd = Rational::gcd(numerator, denominator) * denominator.signum();
+
+        Rational {
+            numerator: numerator / gcd,
+            denominator: denominator / gcd,
+        }
+    }
+
+    // Arithmetic is exact, so rather than silently wrapping (or panicking in debug builds), every
+    // operation reports an error if an intermediate value won't fit in an i128
+    fn checked_add(self, addend: Self) -> Result<Self, Box<dyn Error>> {
+        // Scaling to the least common denominator keeps intermediate values as small as possible
+        let gcd = Rational::gcd(self.denominator, addend.denominator);
+        let (scale, addend_scale) = (addend.denominator / gcd, self.denominator / gcd);
+
+        let numerator = self
+            .numerator
+            .checked_mul(scale)
+            .zip(addend.numerator.checked_mul(addend_scale))
+            .and_then(|(a, b)| a.checked_add(b))
+            .ok_or(OVERFLOW)?;
+
+        let denominator = self.denominator.checked_mul(scale).ok_or(OVERFLOW)?;
+
+        Ok(Rational::new(numerator, denominator))
+    }
+
+    fn checked_sub(self, subtrahend: Self) -> Result<Self, Box<dyn Error>> {
+        self.checked_add(subtrahend.checked_neg()?)
+    }
+
+    fn checked_neg(self) -> Result<Self, Box<dyn Error>> {
+        Ok(Rational {
+            numerator: self.numerator.checked_neg().ok_or(OVERFLOW)?,
+            denominator: self.denominator,
+        })
+    }
+
+    fn checked_mul(self, factor: Self) -> Result<Self, Box<dyn Error>> {
+        // Cancel common factors before multiplying so the products are already in lowest terms
+        let a = Rational::gcd(self.numerator, factor.denominator);
+        let b = Rational::gcd(factor.numerator, self.denominator);
+
+        let numerator = (self.numerator / a)
+            .checked_mul(factor.numerator / b)
+            .ok_or(OVERFLOW)?;
+
+        let denominator = (self.denominator / b)
+            .checked_mul(factor.denominator / a)
+            .ok_or(OVERFLOW)?;
+
+        Ok(Rational::new(numerator, denominator))
+    }
+
+    fn checked_div(self, divisor: Self) -> Result<Self, Box<dyn Error>> {
+        if divisor == Rational::ZERO {
+            return Err("Division by zero".into());
+        }
+
+        self.checked_mul(Rational::new(divisor.denominator, divisor.numerator))
+    }
+
+    fn gcd(a: i128, b: i128) -> i128 {
+        let (mut a, mut b) = (a.abs(), b.abs());
+
+        while b != 0 {
+            (a, b) = (b, a % b);
+        }
+
+        a
+    }
+}
+
+impl From<i128> for Rational {
+    fn from(value: i128) -> Self {
+        Rational {
+            numerator: value,
+            denominator: 1,
+        }
+    }
+}
+
+impl Display for Rational {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.denominator == 1 {
+            write!(f, "{}", self.numerator)
+        } else {
+            write!(f, "{}/{}", self.numerator, self.denominator)
+        }
+    }
+}
+
+// A linear function of an unknown, i.e. `slope * x + intercept`
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+struct Linear {
+    slope: Rational,
+    intercept: Rational,
+}
+
+impl Linear {
+    fn constant(value: Rational) -> Self {
+        Linear {
+            slope: Rational::ZERO,
+            intercept: value,
+        }
+    }
+
+    fn is_constant(&self) -> bool {
+        self.slope == Rational::ZERO
+    }
+
+    fn checked_add(self, addend: Self) -> Result<Self, Box<dyn Error>> {
+        Ok(Linear {
+            slope: self.slope.checked_add(addend.slope)?,
+            intercept: self.intercept.checked_add(addend.intercept)?,
+        })
+    }
+
+    fn checked_sub(self, subtrahend: Self) -> Result<Self, Box<dyn Error>> {
+        Ok(Linear {
+            slope: self.slope.checked_sub(subtrahend.slope)?,
+            intercept: self.intercept.checked_sub(subtrahend.intercept)?,
+        })
+    }
+
+    fn checked_mul(self, factor: Rational) -> Result<Self, Box<dyn Error>> {
+        Ok(Linear {
+            slope: self.slope.checked_mul(factor)?,
+            intercept: self.intercept.checked_mul(factor)?,
+        })
+    }
 }
 
 #[cfg(test)]
@@ -150,12 +578,196 @@ mod test {
     #[test]
     fn test_eval() {
         let monkeys = YellingMonkeys::from_str(TEST_MONKEYS).unwrap();
-        assert_eq!(152f64, monkeys.eval("root"));
+        assert_eq!(Rational::from(152), monkeys.eval("root").unwrap());
     }
 
     #[test]
     fn test_find_human_number() {
         let monkeys = YellingMonkeys::from_str(TEST_MONKEYS).unwrap();
-        assert_eq!(301f64, monkeys.find_human_number());
+        assert_eq!(301, monkeys.find_human_number().unwrap());
+
+        // Large intermediate values would lose precision as floating-point numbers
+        let monkeys = YellingMonkeys::from_str(indoc! {"
+            root: left + right
+            left: scaled - humn
+            scaled: humn * big
+            big: 1000000000000000001
+            right: 1000000000000000000000000000000
+            humn: 0"
+        })
+        .unwrap();
+
+        assert_eq!(1_000_000_000_000, monkeys.find_human_number().unwrap());
+    }
+
+    #[test]
+    fn test_find_human_number_errors() {
+        // humn * humn is not linear
+        assert!(YellingMonkeys::from_str(indoc! {"
+            root: a + b
+            a: humn * humn
+            b: 4
+            humn: 0"
+        })
+        .unwrap()
+        .find_human_number()
+        .is_err());
+
+        // 2 * humn = 3 has no integer solution
+        assert!(YellingMonkeys::from_str(indoc! {"
+            root: a + b
+            a: humn * two
+            two: 2
+            b: 3
+            humn: 0"
+        })
+        .unwrap()
+        .find_human_number()
+        .is_err());
+
+        // Multiplying the human's number by 10^40 overflows
+        assert!(YellingMonkeys::from_str(indoc! {"
+            root: a + b
+            a: humn * e40
+            e40: e20 * e20
+            e20: e10 * e10
+            e10: 10000000000
+            b: 3
+            humn: 0"
+        })
+        .unwrap()
+        .find_human_number()
+        .is_err());
+
+        // humn - humn = 1 has no solution at all
+        assert!(YellingMonkeys::from_str(indoc! {"
+            root: a + b
+            a: humn - humn
+            b: 1
+            humn: 0"
+        })
+        .unwrap()
+        .find_human_number()
+        .is_err());
+    }
+
+    #[test]
+    fn test_validation() {
+        assert!(YellingMonkeys::from_str(indoc! {"
+            root: a + b
+            a: 1
+            b: 2
+            a: 3"
+        })
+        .is_err());
+
+        assert!(YellingMonkeys::from_str(indoc! {"
+            root: a + b
+            a: 1"
+        })
+        .is_err());
+
+        assert!(YellingMonkeys::from_str(indoc! {"
+            root: a + b
+            a: b * c
+            b: c - a
+            c: 7"
+        })
+        .is_err());
+
+        // Shared operands are fine, and only evaluated once
+        let monkeys = YellingMonkeys::from_str(indoc! {"
+            root: a + a
+            a: b * b
+            b: 7"
+        })
+        .unwrap();
+
+        assert_eq!(Rational::from(98), monkeys.eval("root").unwrap());
+    }
+
+    #[test]
+    fn test_custom_names() {
+        let mut monkeys = YellingMonkeys::from_str(&TEST_MONKEYS.replace("humn", "me")).unwrap();
+        monkeys.human = String::from("me");
+        assert_eq!(301, monkeys.find_human_number().unwrap());
+
+        // Check that `sjmn` matches `cczh` instead, solving for `dvpt`
+        let monkeys = {
+            let mut monkeys =
+                YellingMonkeys::from_str(&format!("{}\ncheck: sjmn - cczh", TEST_MONKEYS)).unwrap();
+
+            monkeys.root = String::from("check");
+            monkeys.human = String::from("dvpt");
+            monkeys
+        };
+
+        // 150 = 4 + 2 * (5 - dvpt)
+        assert_eq!(-68, monkeys.find_human_number().unwrap());
+        assert_eq!(
+            "(4 + (2 * (5 - dvpt)))",
+            monkeys.equation().unwrap().1.to_string()
+        );
+    }
+
+    #[test]
+    fn test_expression() {
+        let monkeys = YellingMonkeys::from_str(TEST_MONKEYS).unwrap();
+
+        let (left, right) = monkeys.equation().unwrap();
+        assert_eq!("((4 + (2 * (humn - 3))) / 4)", left.to_string());
+        assert_eq!("150", right.to_string());
+
+        assert_eq!(
+            Expression::Constant(Rational::from(30)),
+            monkeys.expression("drzm").unwrap()
+        );
+
+        assert_eq!(
+            indoc! {r#"
+                digraph expression {
+                    n0 [label="-", shape=circle];
+                    n1 [label="humn", shape=doublecircle];
+                    n0 -> n1;
+                    n2 [label="3", shape=box];
+                    n0 -> n2;
+                }
+            "#},
+            monkeys.expression("ptdq").unwrap().to_dot()
+        );
+    }
+
+    #[test]
+    fn test_rational() {
+        assert_eq!(Rational::new(1, 2), Rational::new(-3, -6));
+        assert_eq!(Rational::new(-1, 3), Rational::new(2, -6));
+        assert_eq!(
+            Rational::new(5, 6),
+            Rational::new(1, 2)
+                .checked_add(Rational::new(1, 3))
+                .unwrap()
+        );
+        assert_eq!(
+            Rational::from(2),
+            Rational::new(4, 3)
+                .checked_div(Rational::new(2, 3))
+                .unwrap()
+        );
+
+        // Common factors cancel out before multiplying, so this doesn't overflow
+        let big = i128::MAX / 2;
+        assert_eq!(
+            Rational::ONE,
+            Rational::new(big, 3)
+                .checked_mul(Rational::new(3, big))
+                .unwrap()
+        );
+
+        assert!(Rational::from(big).checked_mul(Rational::from(3)).is_err());
+        assert!(Rational::from(i128::MAX)
+            .checked_add(Rational::ONE)
+            .is_err());
+        assert!(Rational::ONE.checked_div(Rational::ZERO).is_err());
+        assert_eq!("-7/2", Rational::new(7, -2).to_string());
     }
 }
@@ -8,11 +8,18 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     if let Some(path) = args.get(1) {
         let map = MonkeyMap::from_str(fs::read_to_string(path)?.as_str())?;
-        println!("Password from map: {}", map.password());
+
+        println!("Password from map: {}", map.password(Wrapping::Flat)?);
+        println!("Password from cube: {}", map.password(Wrapping::Cube)?);
+
+        if let Some(trace_path) = args.get(2) {
+            let path = map.walk(Wrapping::Cube)?;
+            fs::write(trace_path, PathOverlay::new(&map, &path).to_string())?;
+        }
 
         Ok(())
     } else {
-        Err("Usage: day22 INPUT_FILE_PATH".into())
+        Err("Usage: day22 INPUT_FILE_PATH [CUBE_TRACE_OUTPUT_PATH]".into())
     }
 }
 
@@ -21,6 +28,11 @@ struct MonkeyMap {
     width: usize,
 
     instructions: Vec<Instruction>,
+
+    flat_wraps: WrapTable,
+
+    // `None` if the map isn't a valid cube net
+    cube_wraps: Option<WrapTable>,
 }
 
 impl MonkeyMap {
@@ -28,45 +40,67 @@ impl MonkeyMap {
         (y * self.width) + x
     }
 
-    fn password(&self) -> u32 {
-        let mut heading = Heading::Right;
-        let mut y = 0;
-        let mut x = self
-            .tiles
-            .iter()
-            .enumerate()
-            .find(|(_, tile)| !matches!(tile, MapTile::Void))
-            .unwrap()
-            .0;
+    fn height(&self) -> usize {
+        self.tiles.len() / self.width
+    }
+
+    // Returns every position we occupy while following the instructions, including the start; each
+    // turn adds another entry for the same tile with the new heading
+    fn walk(&self, wrapping: Wrapping) -> Result<Vec<Position>, Box<dyn Error>> {
+        let wraps = match wrapping {
+            Wrapping::Flat => &self.flat_wraps,
+            Wrapping::Cube => self
+                .cube_wraps
+                .as_ref()
+                .ok_or("Map can't be folded into a cube")?,
+        };
+
+        let mut position = Position {
+            x: self
+                .tiles
+                .iter()
+                .position(|tile| !matches!(tile, MapTile::Void))
+                .ok_or("Map must not be blank")?,
+            y: 0,
+            heading: Heading::Right,
+        };
+
+        let mut path = vec![position];
 
         for instruction in &self.instructions {
             match instruction {
                 Instruction::TurnLeft => {
-                    heading = match heading {
+                    position.heading = match position.heading {
                         Heading::Up => Heading::Left,
                         Heading::Down => Heading::Right,
                         Heading::Left => Heading::Down,
                         Heading::Right => Heading::Up,
-                    }
+                    };
+
+                    path.push(position);
                 }
 
                 Instruction::TurnRight => {
-                    heading = match heading {
+                    position.heading = match position.heading {
                         Heading::Up => Heading::Right,
                         Heading::Down => Heading::Left,
                         Heading::Left => Heading::Up,
                         Heading::Right => Heading::Down,
-                    }
+                    };
+
+                    path.push(position);
                 }
 
                 Instruction::Advance(distance) => {
                     for _ in 0..*distance {
-                        let (next_x, next_y) = self.next_tile(x, y, &heading);
+                        let next = self
+                            .neighbor(&position)
+                            .unwrap_or_else(|| wraps.target(&position));
 
-                        match self.tiles[self.index(next_x, next_y)] {
+                        match self.tiles[self.index(next.x, next.y)] {
                             MapTile::Open => {
-                                x = next_x;
-                                y = next_y;
+                                position = next;
+                                path.push(position);
                             }
                             MapTile::Wall => break,
                             MapTile::Void => unreachable!(),
@@ -76,16 +110,74 @@ impl MonkeyMap {
             }
         }
 
-        let facing_score = match heading {
+        Ok(path)
+    }
+
+    fn password(&self, wrapping: Wrapping) -> Result<u32, Box<dyn Error>> {
+        let position = *self.walk(wrapping)?.last().unwrap();
+
+        let facing_score = match position.heading {
             Heading::Up => 3,
             Heading::Down => 1,
             Heading::Left => 2,
             Heading::Right => 0,
         };
 
-        ((1000 * (y + 1)) + (4 * (x + 1)) + facing_score) as u32
+        Ok(((1000 * (position.y + 1)) + (4 * (position.x + 1)) + facing_score) as u32)
+    }
+
+    // Returns the adjacent tile in the given position's heading if it's on the map without wrapping
+    fn neighbor(&self, position: &Position) -> Option<Position> {
+        let (x, y) = match position.heading {
+            Heading::Up => (position.x, position.y.checked_sub(1)?),
+            Heading::Down => (position.x, position.y + 1),
+            Heading::Left => (position.x.checked_sub(1)?, position.y),
+            Heading::Right => (position.x + 1, position.y),
+        };
+
+        if x < self.width
+            && y < self.height()
+            && !matches!(self.tiles[self.index(x, y)], MapTile::Void)
+        {
+            Some(Position {
+                x,
+                y,
+                heading: position.heading,
+            })
+        } else {
+            None
+        }
     }
 
+    // Finds the destination for every step that would walk off the edge of the map (or into a gap in
+    // the middle of it) ahead of time, so walking never has to search for a place to wrap to
+    fn wrap_table(&self, wrap: impl Fn(&Position) -> Position) -> WrapTable {
+        let mut targets = vec![None; self.tiles.len() * 4];
+
+        for y in 0..self.height() {
+            for x in 0..self.width {
+                if matches!(self.tiles[self.index(x, y)], MapTile::Void) {
+                    continue;
+                }
+
+                for heading in [Heading::Up, Heading::Down, Heading::Left, Heading::Right] {
+                    let position = Position { x, y, heading };
+
+                    if self.neighbor(&position).is_none() {
+                        targets[WrapTable::index(self.width, &position)] = Some(wrap(&position));
+                    }
+                }
+            }
+        }
+
+        WrapTable {
+            width: self.width,
+            targets,
+        }
+    }
+
+    // Searches for the next tile in the given heading, skipping over any void; this is only used to
+    // build the flat wrap table
     fn next_tile(&self, x: usize, y: usize, heading: &Heading) -> (usize, usize) {
         match heading {
             Heading::Up => {
@@ -212,11 +304,28 @@ impl FromStr for MonkeyMap {
                 instructions
             };
 
-            Ok(MonkeyMap {
+            let mut map = MonkeyMap {
                 tiles,
                 width,
                 instructions,
-            })
+                flat_wraps: WrapTable::default(),
+                cube_wraps: None,
+            };
+
+            map.flat_wraps = map.wrap_table(|position| {
+                let (x, y) = map.next_tile(position.x, position.y, &position.heading);
+
+                Position {
+                    x,
+                    y,
+                    heading: position.heading,
+                }
+            });
+
+            map.cube_wraps = Cube::fold(&map.tiles, map.width)
+                .map(|cube| map.wrap_table(|position| cube.wrap(position)));
+
+            Ok(map)
         } else {
             Err("Could not parse map/directions".into())
         }
@@ -242,6 +351,289 @@ impl Display for MonkeyMap {
     }
 }
 
+// Draws the map with `>`, `v`, `<`, and `^` marking the most recent heading on each visited tile
+struct PathOverlay<'a> {
+    map: &'a MonkeyMap,
+    path: &'a [Position],
+}
+
+impl<'a> PathOverlay<'a> {
+    fn new(map: &'a MonkeyMap, path: &'a [Position]) -> Self {
+        PathOverlay { map, path }
+    }
+}
+
+impl Display for PathOverlay<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mut characters: Vec<char> = self
+            .map
+            .tiles
+            .iter()
+            .map(|tile| match tile {
+                MapTile::Open => '.',
+                MapTile::Wall => '#',
+                MapTile::Void => ' ',
+            })
+            .collect();
+
+        for position in self.path {
+            characters[self.map.index(position.x, position.y)] = match position.heading {
+                Heading::Up => '^',
+                Heading::Down => 'v',
+                Heading::Left => '<',
+                Heading::Right => '>',
+            };
+        }
+
+        characters
+            .chunks_exact(self.map.width)
+            .try_for_each(|chunk| writeln!(f, "{}", chunk.iter().collect::<String>()))
+    }
+}
+
+#[derive(Default)]
+struct WrapTable {
+    width: usize,
+
+    // Indexed by tile and then by heading; only positions at the edge of the map have targets
+    targets: Vec<Option<Position>>,
+}
+
+impl WrapTable {
+    fn index(width: usize, position: &Position) -> usize {
+        (((position.y * width) + position.x) * 4) + position.heading as usize
+    }
+
+    fn target(&self, position: &Position) -> Position {
+        self.targets[WrapTable::index(self.width, position)]
+            .expect("Position should be at the edge of the map")
+    }
+}
+
+// Each face of the cube, along with its orientation in space once the map has been folded up.
+// Directions are unit vectors; `right` and `down` point in the directions of increasing x and y on
+// the map, and `normal` points out of the cube.
+struct Cube {
+    face_size: usize,
+    faces: Vec<CubeFace>,
+}
+
+struct CubeFace {
+    // The position of the face in the grid of faces, e.g. (1, 0) is the second face in the top row
+    column: usize,
+    row: usize,
+
+    normal: [i32; 3],
+    right: [i32; 3],
+    down: [i32; 3],
+}
+
+impl Cube {
+    fn fold(tiles: &[MapTile], width: usize) -> Option<Self> {
+        let height = tiles.len() / width;
+
+        let area = tiles
+            .iter()
+            .filter(|tile| !matches!(tile, MapTile::Void))
+            .count();
+
+        let face_size = (1..=width.max(height)).find(|size| size * size * 6 == area)?;
+
+        if !width.is_multiple_of(face_size) || !height.is_multiple_of(face_size) {
+            return None;
+        }
+
+        let (columns, rows) = (width / face_size, height / face_size);
+
+        let is_face = |column: usize, row: usize| {
+            !matches!(
+                tiles[(row * face_size * width) + (column * face_size)],
+                MapTile::Void
+            )
+        };
+
+        let (column, row) = (0..rows)
+            .flat_map(|row| (0..columns).map(move |column| (column, row)))
+            .find(|&(column, row)| is_face(column, row))?;
+
+        let mut faces = vec![CubeFace {
+            column,
+            row,
+            normal: [0, 0, -1],
+            right: [1, 0, 0],
+            down: [0, 1, 0],
+        }];
+
+        // Explore the net, folding each new face over the edge it shares with a face we've already
+        // placed
+        let mut i = 0;
+
+        while i < faces.len() {
+            let face = &faces[i];
+
+            let neighbors = [
+                (
+                    face.column + 1,
+                    face.row,
+                    CubeFace {
+                        column: 0,
+                        row: 0,
+                        normal: face.right,
+                        right: negate(face.normal),
+                        down: face.down,
+                    },
+                ),
+                (
+                    face.column.wrapping_sub(1),
+                    face.row,
+                    CubeFace {
+                        column: 0,
+                        row: 0,
+                        normal: negate(face.right),
+                        right: face.normal,
+                        down: face.down,
+                    },
+                ),
+                (
+                    face.column,
+                    face.row + 1,
+                    CubeFace {
+                        column: 0,
+                        row: 0,
+                        normal: face.down,
+                        right: face.right,
+                        down: negate(face.normal),
+                    },
+                ),
+                (
+                    face.column,
+                    face.row.wrapping_sub(1),
+                    CubeFace {
+                        column: 0,
+                        row: 0,
+                        normal: negate(face.down),
+                        right: face.right,
+                        down: face.normal,
+                    },
+                ),
+            ];
+
+            for (column, row, neighbor) in neighbors {
+                if column < columns
+                    && row < rows
+                    && is_face(column, row)
+                    && !faces
+                        .iter()
+                        .any(|face| face.column == column && face.row == row)
+                {
+                    faces.push(CubeFace {
+                        column,
+                        row,
+                        ..neighbor
+                    });
+                }
+            }
+
+            i += 1;
+        }
+
+        // A valid net covers all six sides of the cube exactly once
+        let all_sides = faces.len() == 6
+            && (1..faces.len())
+                .all(|i| faces[..i].iter().all(|face| face.normal != faces[i].normal));
+
+        if all_sides {
+            Some(Cube { face_size, faces })
+        } else {
+            None
+        }
+    }
+
+    // Returns where we'd end up (and which way we'd be facing) if we walked off the edge of a face
+    fn wrap(&self, position: &Position) -> Position {
+        let size = self.face_size as i32;
+
+        let face = self
+            .faces
+            .iter()
+            .find(|face| {
+                face.column == position.x / self.face_size
+                    && face.row == position.y / self.face_size
+            })
+            .expect("Position should be on a face");
+
+        // Work in coordinates where the cube spans from -size to size on every axis and each tile's
+        // center has odd coordinates
+        let (i, j) = (
+            (position.x % self.face_size) as i32,
+            (position.y % self.face_size) as i32,
+        );
+
+        let point = add(
+            add(
+                scale(face.normal, size),
+                scale(face.right, 2 * i - size + 1),
+            ),
+            scale(face.down, 2 * j - size + 1),
+        );
+
+        let direction = match position.heading {
+            Heading::Up => negate(face.down),
+            Heading::Down => face.down,
+            Heading::Left => negate(face.right),
+            Heading::Right => face.right,
+        };
+
+        // Step over the edge and down the side of the cube
+        let point = add(add(point, direction), negate(face.normal));
+
+        let next_face = self
+            .faces
+            .iter()
+            .find(|next_face| next_face.normal == direction)
+            .expect("Every direction should lead to a face");
+
+        let next_direction = negate(face.normal);
+
+        let heading = if next_direction == next_face.right {
+            Heading::Right
+        } else if next_direction == negate(next_face.right) {
+            Heading::Left
+        } else if next_direction == next_face.down {
+            Heading::Down
+        } else {
+            Heading::Up
+        };
+
+        let (i, j) = (
+            ((dot(point, next_face.right) + size - 1) / 2) as usize,
+            ((dot(point, next_face.down) + size - 1) / 2) as usize,
+        );
+
+        Position {
+            x: next_face.column * self.face_size + i,
+            y: next_face.row * self.face_size + j,
+            heading,
+        }
+    }
+}
+
+fn add(a: [i32; 3], b: [i32; 3]) -> [i32; 3] {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+fn negate(a: [i32; 3]) -> [i32; 3] {
+    scale(a, -1)
+}
+
+fn scale(a: [i32; 3], factor: i32) -> [i32; 3] {
+    a.map(|component| component * factor)
+}
+
+fn dot(a: [i32; 3], b: [i32; 3]) -> i32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
 #[derive(Copy, Clone)]
 enum MapTile {
     Open,
@@ -256,7 +648,23 @@ enum Instruction {
     TurnRight,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Wrapping {
+    // Walking off an edge of the map brings us back on the opposite edge
+    Flat,
+
+    // The map is folded up into a cube and we walk around its surface
+    Cube,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+struct Position {
+    x: usize,
+    y: usize,
+    heading: Heading,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
 enum Heading {
     Up,
     Down,
@@ -264,6 +672,18 @@ enum Heading {
     Right,
 }
 
+#[cfg(test)]
+impl Heading {
+    fn reverse(&self) -> Self {
+        match self {
+            Heading::Up => Heading::Down,
+            Heading::Down => Heading::Up,
+            Heading::Left => Heading::Right,
+            Heading::Right => Heading::Left,
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -289,6 +709,160 @@ mod test {
     #[test]
     fn test_password() {
         let map = MonkeyMap::from_str(TEST_MAP).unwrap();
-        assert_eq!(6032, map.password());
+        assert_eq!(6032, map.password(Wrapping::Flat).unwrap());
+        assert_eq!(5031, map.password(Wrapping::Cube).unwrap());
+    }
+
+    #[test]
+    fn test_path_overlay() {
+        let map = MonkeyMap::from_str(TEST_MAP).unwrap();
+
+        {
+            let path = map.walk(Wrapping::Flat).unwrap();
+
+            assert_eq!(
+                indoc! {"
+                            >>v#    
+                            .#v.    
+                            #.v.    
+                            ..v.    
+                    ...#...v..v#    
+                    >>>v...>#.>>    
+                    ..#v...#....    
+                    ...>>>>v..#.    
+                            ...#....
+                            .....#..
+                            .#......
+                            ......#.
+                "},
+                PathOverlay::new(&map, &path).to_string()
+            );
+        }
+
+        {
+            let path = map.walk(Wrapping::Cube).unwrap();
+
+            assert_eq!(
+                indoc! {"
+                            >>v#    
+                            .#v.    
+                            #.v.    
+                            ..v.    
+                    ...#..^...v#    
+                    .>>>>>^.#.>>    
+                    .^#....#....    
+                    .^........#.    
+                            ...#..v.
+                            .....#v.
+                            .#v<<<<.
+                            ..v...#.
+                "},
+                PathOverlay::new(&map, &path).to_string()
+            );
+        }
+    }
+
+    #[test]
+    fn test_flat_wrap() {
+        let map = MonkeyMap::from_str(TEST_MAP).unwrap();
+
+        assert_eq!(
+            Position {
+                x: 0,
+                y: 5,
+                heading: Heading::Right
+            },
+            map.flat_wraps.target(&Position {
+                x: 11,
+                y: 5,
+                heading: Heading::Right
+            })
+        );
+
+        assert_eq!(
+            Position {
+                x: 5,
+                y: 7,
+                heading: Heading::Up
+            },
+            map.flat_wraps.target(&Position {
+                x: 5,
+                y: 4,
+                heading: Heading::Up
+            })
+        );
+
+        // Gaps in the middle of a row get skipped, too
+        let map = MonkeyMap::from_str(".. ..\n\n1").unwrap();
+
+        assert_eq!(
+            Position {
+                x: 3,
+                y: 0,
+                heading: Heading::Right
+            },
+            map.flat_wraps.target(&Position {
+                x: 1,
+                y: 0,
+                heading: Heading::Right
+            })
+        );
+    }
+
+    #[test]
+    fn test_cube_wrap() {
+        let map = MonkeyMap::from_str(TEST_MAP).unwrap();
+        let wraps = map.cube_wraps.as_ref().unwrap();
+
+        // The examples from the puzzle description
+        assert_eq!(
+            Position {
+                x: 14,
+                y: 8,
+                heading: Heading::Down
+            },
+            wraps.target(&Position {
+                x: 11,
+                y: 5,
+                heading: Heading::Right
+            })
+        );
+
+        assert_eq!(
+            Position {
+                x: 1,
+                y: 7,
+                heading: Heading::Up
+            },
+            wraps.target(&Position {
+                x: 10,
+                y: 11,
+                heading: Heading::Down
+            })
+        );
+
+        // Wrapping in every direction from every edge tile and turning around should always lead
+        // back to where we started
+        for y in 0..map.height() {
+            for x in 0..map.width {
+                for heading in [Heading::Up, Heading::Down, Heading::Left, Heading::Right] {
+                    let position = Position { x, y, heading };
+
+                    if matches!(map.tiles[map.index(x, y)], MapTile::Void)
+                        || map.neighbor(&position).is_some()
+                    {
+                        continue;
+                    }
+
+                    let mut wrapped = wraps.target(&position);
+                    wrapped.heading = wrapped.heading.reverse();
+
+                    let mut returned = wraps.target(&wrapped);
+                    returned.heading = returned.heading.reverse();
+
+                    assert_eq!(position, returned);
+                }
+            }
+        }
     }
 }
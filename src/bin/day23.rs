@@ -1,6 +1,7 @@
 use std::collections::{HashMap, HashSet};
 use std::error::Error;
 use std::fmt::{Display, Formatter};
+use std::path::Path;
 use std::str::FromStr;
 use std::{fs, iter};
 
@@ -8,7 +9,54 @@ fn main() -> Result<(), Box<dyn Error>> {
     let args: Vec<String> = std::env::args().collect();
 
     if let Some(path) = args.get(1) {
-        let mut grove = Grove::from_str(fs::read_to_string(path)?.as_str())?;
+        let mut rules = if args[2..].iter().any(|option| option == "hexagonal") {
+            Rules::hexagonal()
+        } else if args[2..].iter().any(|option| option == "cubic") {
+            Rules::cubic()
+        } else {
+            Rules::standard()
+        };
+
+        let mut dense = false;
+        let mut replay_round = None;
+        let mut frame_directory = None;
+
+        for option in &args[2..] {
+            if let Some(round) = option.strip_prefix("round=") {
+                replay_round = Some(round.parse::<usize>()?);
+                continue;
+            }
+
+            if let Some(directory) = option.strip_prefix("frames=") {
+                frame_directory = Some(Path::new(directory));
+                continue;
+            }
+
+            match option.as_str() {
+                "hexagonal" | "cubic" => {}
+                "dense" => dense = true,
+                "fixed-order" => rules.rotation = Rotation::Fixed,
+                "first-come" => rules.conflict_resolution = ConflictResolution::FirstCome,
+                _ => return Err(format!("Unrecognized rule option: {}", option).into()),
+            }
+        }
+
+        // The dense backend only knows the standard rules
+        if dense
+            && args[2..]
+                .iter()
+                .any(|option| Rules::OPTIONS.contains(&option.as_str()))
+        {
+            return Err("The dense backend only supports the standard rules".into());
+        }
+
+        let input = fs::read_to_string(path)?;
+
+        let mut grove: Box<dyn Simulation> = if dense {
+            Box::new(DenseGrove::from_str(&input)?)
+        } else {
+            Box::new(Grove::from_str(&input)?.with_rules(rules.clone()))
+        };
 
         for _ in 0..10 {
             grove.advance_round();
@@ -21,88 +69,131 @@ fn main() -> Result<(), Box<dyn Error>> {
 
         while grove.advance_round() {}
 
-        println!("First round without movement: {}", grove.round + 1);
+        println!("First round without movement: {}", grove.round() + 1);
+
+        if replay_round.is_some() || frame_directory.is_some() {
+            let replay = Replay::record(Grove::from_str(&input)?.with_rules(rules));
+
+            if let Some(round) = replay_round {
+                let grove = replay
+                    .round(round)
+                    .ok_or_else(|| format!("Elves stop moving after round {}", replay.len() - 1))?;
+
+                println!();
+                println!("After round {}:", round);
+                print!("{}", grove);
+            }
+
+            if let Some(directory) = frame_directory {
+                replay.export_frames(directory)?;
+                println!(
+                    "Exported {} frames to {}",
+                    replay.len(),
+                    directory.display()
+                );
+            }
+        }
 
         Ok(())
     } else {
-        Err("Usage: day23 INPUT_FILE_PATH".into())
+        Err(
+            "Usage: day23 INPUT_FILE_PATH [dense | [hexagonal|cubic] [fixed-order] [first-come]] \
+                [round=ROUND] [frames=DIRECTORY]"
+                .into(),
+        )
     }
 }
 
+trait Simulation {
+    // Returns true if any elf moved
+    fn advance_round(&mut self) -> bool;
+
+    fn empty_ground_tiles(&self) -> u32;
+
+    // The number of rounds in which at least one elf has moved
+    fn round(&self) -> usize;
+}
+
+#[derive(Clone)]
 struct Grove {
-    elves: HashSet<(i32, i32)>,
+    elves: HashSet<(i32, i32, i32)>,
+    rules: Rules,
     round: usize,
 }
 
 impl Grove {
-    fn bounds(&self) -> ((i32, i32), (i32, i32)) {
+    fn with_rules(self, rules: Rules) -> Self {
+        Grove { rules, ..self }
+    }
+
+    fn bounds(&self) -> ((i32, i32, i32), (i32, i32, i32)) {
         self.elves.iter().fold(
-            ((i32::MAX, i32::MAX), (i32::MIN, i32::MIN)),
-            |bounds, (x, y)| {
+            (
+                (i32::MAX, i32::MAX, i32::MAX),
+                (i32::MIN, i32::MIN, i32::MIN),
+            ),
+            |(min, max), &(x, y, z)| {
                 (
-                    (bounds.0 .0.min(*x), bounds.0 .1.min(*y)),
-                    (bounds.1 .0.max(*x), bounds.1 .1.max(*y)),
+                    (min.0.min(x), min.1.min(y), min.2.min(z)),
+                    (max.0.max(x), max.1.max(y), max.2.max(z)),
                 )
             },
         )
     }
 
     fn empty_ground_tiles(&self) -> u32 {
-        let ((x_min, y_min), (x_max, y_max)) = self.bounds();
-        let area = (x_min.abs_diff(x_max) + 1) * (y_min.abs_diff(y_max) + 1);
+        let ((x_min, y_min, z_min), (x_max, y_max, z_max)) = self.bounds();
+        let volume =
+            (x_min.abs_diff(x_max) + 1) * (y_min.abs_diff(y_max) + 1) * (z_min.abs_diff(z_max) + 1);
 
-        area - self.elves.len() as u32
+        volume - self.elves.len() as u32
     }
 
     fn advance_round(&mut self) -> bool {
-        const SEARCH_ORDER: [Direction; 4] = [
-            Direction::North,
-            Direction::South,
-            Direction::West,
-            Direction::East,
-        ];
+        let occupied = |(x, y, z): (i32, i32, i32), (d_x, d_y, d_z): (i32, i32, i32)| {
+            self.elves.contains(&(x + d_x, y + d_y, z + d_z))
+        };
 
-        let mut proposals: HashMap<(i32, i32), Vec<(i32, i32)>> =
-            HashMap::with_capacity(self.elves.len());
+        // Elves take turns proposing moves in a predictable order, which only matters if the first
+        // elf to propose a move gets to make it
+        let mut elves: Vec<(i32, i32, i32)> = self.elves.iter().copied().collect();
 
-        for (x, y) in &self.elves {
-            let mut has_neighbor = false;
+        if self.rules.conflict_resolution == ConflictResolution::FirstCome {
+            elves.sort_by_key(|&(x, y, z)| (z, y, x));
+        }
 
-            'neighbor_search: for neighbor_x in x - 1..=x + 1 {
-                for neighbor_y in y - 1..=y + 1 {
-                    // Ignore ourselves when searching for neighbors
-                    if !(neighbor_x == *x && neighbor_y == *y)
-                        && self.elves.contains(&(neighbor_x, neighbor_y))
-                    {
-                        has_neighbor = true;
-                        break 'neighbor_search;
-                    }
-                }
+        let mut proposals: HashMap<_, Vec<_>> = HashMap::with_capacity(self.elves.len());
+
+        for elf in elves {
+            let has_neighbor = self
+                .rules
+                .neighborhood
+                .iter()
+                .any(|&offset| occupied(elf, offset));
+
+            if !has_neighbor {
+                continue;
             }
 
-            if has_neighbor {
-                for d in 0..SEARCH_ORDER.len() {
-                    let direction = &SEARCH_ORDER[(d + self.round) % SEARCH_ORDER.len()];
+            let first_direction = match self.rules.rotation {
+                Rotation::Fixed => 0,
+                Rotation::Cycle => self.round,
+            };
 
-                    let mut neighbors: Box<dyn Iterator<Item = (i32, i32)>> = match direction {
-                        Direction::North => Box::new((x - 1..=x + 1).zip(iter::repeat(y - 1))),
-                        Direction::South => Box::new((x - 1..=x + 1).zip(iter::repeat(y + 1))),
-                        Direction::East => Box::new(iter::repeat(x + 1).zip(y - 1..=y + 1)),
-                        Direction::West => Box::new(iter::repeat(x - 1).zip(y - 1..=y + 1)),
-                    };
+            for d in 0..self.rules.directions.len() {
+                let direction =
+                    &self.rules.directions[(first_direction + d) % self.rules.directions.len()];
 
-                    if neighbors.all(|neighbor| !self.elves.contains(&neighbor)) {
-                        let proposal = match direction {
-                            Direction::North => (*x, y - 1),
-                            Direction::South => (*x, y + 1),
-                            Direction::East => (x + 1, *y),
-                            Direction::West => (x - 1, *y),
-                        };
+                if direction.clear.iter().all(|&offset| !occupied(elf, offset)) {
+                    let (x, y, z) = elf;
+                    let (d_x, d_y, d_z) = direction.step;
 
-                        proposals.entry(proposal).or_default().push((*x, *y));
+                    proposals
+                        .entry((x + d_x, y + d_y, z + d_z))
+                        .or_default()
+                        .push(elf);
 
-                        break;
-                    }
+                    break;
                 }
             }
         }
@@ -110,8 +201,13 @@ impl Grove {
         let mut accepted_proposal = false;
 
         for (proposal, elves) in proposals {
-            if elves.len() == 1 {
-                self.elves.remove(&elves[0]);
+            let mover = match self.rules.conflict_resolution {
+                ConflictResolution::Cancel if elves.len() > 1 => None,
+                _ => Some(elves[0]),
+            };
+
+            if let Some(elf) = mover {
+                self.elves.remove(&elf);
                 self.elves.insert(proposal);
 
                 accepted_proposal = true;
@@ -126,6 +222,20 @@ impl Grove {
     }
 }
 
+impl Simulation for Grove {
+    fn advance_round(&mut self) -> bool {
+        Grove::advance_round(self)
+    }
+
+    fn empty_ground_tiles(&self) -> u32 {
+        Grove::empty_ground_tiles(self)
+    }
+
+    fn round(&self) -> usize {
+        self.round
+    }
+}
+
 impl FromStr for Grove {
     type Err = Box<dyn Error>;
 
@@ -135,43 +245,564 @@ impl FromStr for Grove {
         for (y, line) in string.lines().enumerate() {
             line.chars().enumerate().for_each(|(x, c)| {
                 if c == '#' {
-                    elves.insert((x as i32, y as i32));
+                    elves.insert((x as i32, y as i32, 0));
                 }
             });
         }
 
-        Ok(Grove { elves, round: 0 })
+        Ok(Grove {
+            elves,
+            rules: Rules::standard(),
+            round: 0,
+        })
     }
 }
 
 impl Display for Grove {
+    // Draws each layer of the grove from bottom to top, separated by blank lines
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        let ((x_min, y_min), (x_max, y_max)) = self.bounds();
+        let ((x_min, y_min, z_min), (x_max, y_max, z_max)) = self.bounds();
 
-        for y in y_min..=y_max {
-            let mut line = String::new();
+        for z in z_min..=z_max {
+            if z > z_min {
+                writeln!(f)?;
+            }
+
+            for y in y_min..=y_max {
+                let mut line = String::new();
 
-            for x in x_min..=x_max {
-                line.push(if self.elves.contains(&(x, y)) {
-                    '#'
-                } else {
-                    '.'
-                });
+                for x in x_min..=x_max {
+                    line.push(if self.elves.contains(&(x, y, z)) {
+                        '#'
+                    } else {
+                        '.'
+                    });
+                }
+
+                writeln!(f, "{}", line)?;
             }
+        }
+
+        Ok(())
+    }
+}
+
+// Every state of a grove from its initial state until its elves stop moving
+struct Replay {
+    rounds: Vec<Grove>,
+}
+
+impl Replay {
+    fn record(grove: Grove) -> Self {
+        let mut rounds = vec![grove.clone()];
+        let mut grove = grove;
+
+        while grove.advance_round() {
+            rounds.push(grove.clone());
+        }
+
+        Replay { rounds }
+    }
+
+    fn len(&self) -> usize {
+        self.rounds.len()
+    }
+
+    // Returns the grove as it was after the given number of rounds (where round 0 is the initial
+    // state), or `None` if the elves had already stopped moving
+    fn round(&self, round: usize) -> Option<&Grove> {
+        self.rounds.get(round)
+    }
+
+    // Draws every round as a plain PBM image. All frames share the bounds of the whole replay so
+    // they line up when played back as an animation; layers of three-dimensional groves are
+    // flattened into a single image.
+    fn pbm_frames(&self) -> Vec<String> {
+        let ((x_min, y_min, _), (x_max, y_max, _)) = self.rounds.iter().map(Grove::bounds).fold(
+            (
+                (i32::MAX, i32::MAX, i32::MAX),
+                (i32::MIN, i32::MIN, i32::MIN),
+            ),
+            |(min, max), (round_min, round_max)| {
+                (
+                    (
+                        min.0.min(round_min.0),
+                        min.1.min(round_min.1),
+                        min.2.min(round_min.2),
+                    ),
+                    (
+                        max.0.max(round_max.0),
+                        max.1.max(round_max.1),
+                        max.2.max(round_max.2),
+                    ),
+                )
+            },
+        );
+
+        self.rounds
+            .iter()
+            .map(|grove| {
+                let columns: HashSet<(i32, i32)> =
+                    grove.elves.iter().map(|&(x, y, _)| (x, y)).collect();
+
+                let mut frame = format!(
+                    "P1\n{} {}\n",
+                    x_min.abs_diff(x_max) + 1,
+                    y_min.abs_diff(y_max) + 1
+                );
+
+                for y in y_min..=y_max {
+                    for x in x_min..=x_max {
+                        frame.push(if columns.contains(&(x, y)) { '1' } else { '0' });
+                    }
+
+                    frame.push('\n');
+                }
+
+                frame
+            })
+            .collect()
+    }
+
+    fn export_frames(&self, directory: &Path) -> Result<(), Box<dyn Error>> {
+        fs::create_dir_all(directory)?;
 
-            writeln!(f, "{}", line)?;
+        for (round, frame) in self.pbm_frames().iter().enumerate() {
+            fs::write(directory.join(format!("round_{:04}.pbm", round)), frame)?;
         }
 
         Ok(())
     }
 }
 
-#[derive(Debug)]
-enum Direction {
-    North,
-    South,
-    East,
-    West,
+// An alternative to `Grove` for the standard rules that stores each row of the grove as a bitset,
+// so every elf in a 64-tile stretch of a row can look around and choose a direction at once. The
+// bitsets cover a bounding box that grows as the elves spread out.
+struct DenseGrove {
+    // Bits for each row, from the top of the bounding box to the bottom; bit `i` of word `w` is the
+    // tile at x = (64 * w) + i
+    rows: Vec<Vec<u64>>,
+    words_per_row: usize,
+
+    // The position of the top left corner of the bounding box
+    origin: (i32, i32),
+
+    round: usize,
+}
+
+impl DenseGrove {
+    #[cfg(test)]
+    fn elves(&self) -> HashSet<(i32, i32, i32)> {
+        let mut elves = HashSet::new();
+
+        for (y, row) in self.rows.iter().enumerate() {
+            for (w, &word) in row.iter().enumerate() {
+                let mut word = word;
+
+                while word != 0 {
+                    let x = (w * 64) + word.trailing_zeros() as usize;
+                    elves.insert((self.origin.0 + x as i32, self.origin.1 + y as i32, 0));
+
+                    word &= word - 1;
+                }
+            }
+        }
+
+        elves
+    }
+
+    // Makes sure there's at least one empty row and column on every side of the elves so they have
+    // somewhere to go
+    fn ensure_margin(&mut self) {
+        let crowded_rows = self.rows.first().unwrap().iter().any(|&word| word != 0)
+            || self.rows.last().unwrap().iter().any(|&word| word != 0);
+
+        let crowded_columns = self
+            .rows
+            .iter()
+            .any(|row| row[0] & 1 != 0 || row[self.words_per_row - 1] >> 63 != 0);
+
+        if crowded_rows {
+            let margin = self.rows.len();
+            let empty_row = vec![0; self.words_per_row];
+
+            self.rows
+                .splice(0..0, iter::repeat_n(empty_row.clone(), margin));
+            self.rows.extend(iter::repeat_n(empty_row, margin));
+            self.origin.1 -= margin as i32;
+        }
+
+        if crowded_columns {
+            // Add a whole word on each side so we don't have to shift any bits around
+            let margin = self.words_per_row;
+
+            for row in &mut self.rows {
+                row.splice(0..0, iter::repeat_n(0, margin));
+                row.extend(iter::repeat_n(0, margin));
+            }
+
+            self.words_per_row += 2 * margin;
+            self.origin.0 -= (margin * 64) as i32;
+        }
+    }
+
+    fn advance_round(&mut self) -> bool {
+        self.ensure_margin();
+
+        let height = self.rows.len();
+        let empty_row = vec![0; self.words_per_row];
+
+        // Proposals for each row in the order north, south, west, east
+        let proposals = (0..height)
+            .map(|y| {
+                let above = if y > 0 { &self.rows[y - 1] } else { &empty_row };
+                let row = &self.rows[y];
+                let below = self.rows.get(y + 1).unwrap_or(&empty_row);
+
+                let (above_west, above_east) = (shifted(above, -1), shifted(above, 1));
+                let (west, east) = (shifted(row, -1), shifted(row, 1));
+                let (below_west, below_east) = (shifted(below, -1), shifted(below, 1));
+
+                let mut row_proposals = [
+                    vec![0; self.words_per_row],
+                    vec![0; self.words_per_row],
+                    vec![0; self.words_per_row],
+                    vec![0; self.words_per_row],
+                ];
+
+                for w in 0..self.words_per_row {
+                    let north = above_west[w] | above[w] | above_east[w];
+                    let south = below_west[w] | below[w] | below_east[w];
+                    let west_side = above_west[w] | west[w] | below_west[w];
+                    let east_side = above_east[w] | east[w] | below_east[w];
+
+                    let mut undecided = row[w] & (north | south | west[w] | east[w]);
+
+                    for d in 0..4 {
+                        let direction = (d + self.round) % 4;
+
+                        let blocked = match direction {
+                            0 => north,
+                            1 => south,
+                            2 => west_side,
+                            _ => east_side,
+                        };
+
+                        row_proposals[direction][w] = undecided & !blocked;
+                        undecided &= blocked;
+                    }
+                }
+
+                row_proposals
+            })
+            .collect::<Vec<[Vec<u64>; 4]>>();
+
+        let empty_proposals = vec![0; self.words_per_row];
+
+        let proposal = |y: isize, direction: usize| {
+            if y >= 0 && (y as usize) < height {
+                &proposals[y as usize][direction]
+            } else {
+                &empty_proposals
+            }
+        };
+
+        let mut next_rows = self.rows.clone();
+        let mut accepted_proposal = false;
+
+        for y in 0..height {
+            let y_signed = y as isize;
+
+            // Two elves can only propose the same tile if they approach it from opposite sides
+            let north_conflicts = proposal(y_signed - 2, 1);
+            let south_conflicts = proposal(y_signed + 2, 0);
+            let west_conflicts = shifted(&proposals[y][3], -2);
+            let east_conflicts = shifted(&proposals[y][2], 2);
+
+            for w in 0..self.words_per_row {
+                let north = proposals[y][0][w] & !north_conflicts[w];
+                let south = proposals[y][1][w] & !south_conflicts[w];
+                let west = proposals[y][2][w] & !west_conflicts[w];
+                let east = proposals[y][3][w] & !east_conflicts[w];
+
+                let movers = north | south | west | east;
+
+                if movers != 0 {
+                    accepted_proposal = true;
+
+                    next_rows[y][w] &= !movers;
+                    next_rows[y - 1][w] |= north;
+                    next_rows[y + 1][w] |= south;
+                }
+            }
+
+            // Sideways moves may cross word boundaries, so handle them for the whole row at once
+            let west = (0..self.words_per_row)
+                .map(|w| proposals[y][2][w] & !west_conflicts[w])
+                .collect::<Vec<u64>>();
+
+            let east = (0..self.words_per_row)
+                .map(|w| proposals[y][3][w] & !east_conflicts[w])
+                .collect::<Vec<u64>>();
+
+            let (moved_west, moved_east) = (shifted(&west, 1), shifted(&east, -1));
+
+            for w in 0..self.words_per_row {
+                next_rows[y][w] |= moved_west[w] | moved_east[w];
+            }
+        }
+
+        self.rows = next_rows;
+
+        if accepted_proposal {
+            self.round += 1;
+        }
+
+        accepted_proposal
+    }
+
+    fn empty_ground_tiles(&self) -> u32 {
+        let occupied_rows: Vec<usize> = (0..self.rows.len())
+            .filter(|&y| self.rows[y].iter().any(|&word| word != 0))
+            .collect();
+
+        let (Some(&top), Some(&bottom)) = (occupied_rows.first(), occupied_rows.last()) else {
+            return 0;
+        };
+
+        // Fold every row into one so we can find the leftmost and rightmost occupied columns
+        let columns = (0..self.words_per_row)
+            .map(|w| self.rows.iter().fold(0, |columns, row| columns | row[w]))
+            .collect::<Vec<u64>>();
+
+        let left = columns
+            .iter()
+            .position(|&word| word != 0)
+            .map(|w| (w * 64) + columns[w].trailing_zeros() as usize)
+            .unwrap();
+
+        let right = columns
+            .iter()
+            .rposition(|&word| word != 0)
+            .map(|w| (w * 64) + 63 - columns[w].leading_zeros() as usize)
+            .unwrap();
+
+        let elves: u32 = self
+            .rows
+            .iter()
+            .flat_map(|row| row.iter())
+            .map(|word| word.count_ones())
+            .sum();
+
+        ((bottom - top + 1) * (right - left + 1)) as u32 - elves
+    }
+}
+
+// Returns a copy of the given row of bits in which bit `x` is bit `x + offset` of the original row,
+// filling in zeroes past either end
+fn shifted(row: &[u64], offset: i32) -> Vec<u64> {
+    let words = row.len();
+    let bits = offset.unsigned_abs();
+
+    (0..words)
+        .map(|w| {
+            if offset > 0 {
+                let next = if w + 1 < words { row[w + 1] } else { 0 };
+                (row[w] >> bits) | (next << (64 - bits))
+            } else {
+                let previous = if w > 0 { row[w - 1] } else { 0 };
+                (row[w] << bits) | (previous >> (64 - bits))
+            }
+        })
+        .collect()
+}
+
+impl Simulation for DenseGrove {
+    fn advance_round(&mut self) -> bool {
+        DenseGrove::advance_round(self)
+    }
+
+    fn empty_ground_tiles(&self) -> u32 {
+        DenseGrove::empty_ground_tiles(self)
+    }
+
+    fn round(&self) -> usize {
+        self.round
+    }
+}
+
+impl FromStr for DenseGrove {
+    type Err = Box<dyn Error>;
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        let width = string.lines().map(|line| line.len()).max().unwrap_or(0);
+        let words_per_row = (width / 64) + 1;
+
+        let rows = string
+            .lines()
+            .map(|line| {
+                let mut row = vec![0; words_per_row];
+
+                line.chars()
+                    .enumerate()
+                    .filter(|(_, c)| *c == '#')
+                    .for_each(|(x, _)| row[x / 64] |= 1 << (x % 64));
+
+                row
+            })
+            .collect::<Vec<Vec<u64>>>();
+
+        if rows.is_empty() {
+            return Err("Grove must not be empty".into());
+        }
+
+        Ok(DenseGrove {
+            rows,
+            words_per_row,
+            origin: (0, 0),
+            round: 0,
+        })
+    }
+}
+
+// Describes how elves spread out. Positions are three-dimensional so rules can describe grids with
+// more dimensions, but the standard rules (and the puzzle input) only use the plane where z = 0.
+// Offsets are relative to an elf's current position.
+#[derive(Clone)]
+struct Rules {
+    // An elf with no other elves in any of these positions stays put
+    neighborhood: Vec<(i32, i32, i32)>,
+
+    // The directions an elf considers moving in, in the order they're considered in the first round
+    directions: Vec<DirectionRule>,
+
+    rotation: Rotation,
+    conflict_resolution: ConflictResolution,
+}
+
+#[derive(Clone)]
+struct DirectionRule {
+    step: (i32, i32, i32),
+
+    // An elf only proposes moving in this direction if all of these positions are empty
+    clear: Vec<(i32, i32, i32)>,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Rotation {
+    // Every round, the first direction to consider moves to the end of the list
+    Cycle,
+
+    // Elves consider directions in the same order every round
+    Fixed,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum ConflictResolution {
+    // If more than one elf proposes moving to the same position, none of them move
+    Cancel,
+
+    // If more than one elf proposes moving to the same position, the one that comes first in reading
+    // order moves and the others stay put
+    FirstCome,
+}
+
+impl Rules {
+    // Command-line options that change the rules from the standard ones
+    const OPTIONS: [&'static str; 4] = ["hexagonal", "cubic", "fixed-order", "first-come"];
+
+    // The rules from the puzzle: eight neighbors, and directions considered in the order north,
+    // south, west, east
+    fn standard() -> Self {
+        let direction = |step: (i32, i32), sideways: (i32, i32)| DirectionRule {
+            step: (step.0, step.1, 0),
+            clear: (-1..=1)
+                .map(|i| (step.0 + sideways.0 * i, step.1 + sideways.1 * i, 0))
+                .collect(),
+        };
+
+        Rules {
+            neighborhood: Rules::neighborhood(2),
+            directions: vec![
+                direction((0, -1), (1, 0)),
+                direction((0, 1), (1, 0)),
+                direction((-1, 0), (0, 1)),
+                direction((1, 0), (0, 1)),
+            ],
+            rotation: Rotation::Cycle,
+            conflict_resolution: ConflictResolution::Cancel,
+        }
+    }
+
+    // Hexagonal cells in axial coordinates (where the third axis is implicitly -x - y); an elf
+    // moves in one of six directions if that cell and the two cells on either side of it are clear
+    fn hexagonal() -> Self {
+        const HEX_DIRECTIONS: [(i32, i32, i32); 6] = [
+            (1, -1, 0),
+            (1, 0, 0),
+            (0, 1, 0),
+            (-1, 1, 0),
+            (-1, 0, 0),
+            (0, -1, 0),
+        ];
+
+        Rules {
+            neighborhood: Vec::from(HEX_DIRECTIONS),
+            directions: (0..HEX_DIRECTIONS.len())
+                .map(|i| DirectionRule {
+                    step: HEX_DIRECTIONS[i],
+                    clear: vec![
+                        HEX_DIRECTIONS[(i + HEX_DIRECTIONS.len() - 1) % HEX_DIRECTIONS.len()],
+                        HEX_DIRECTIONS[i],
+                        HEX_DIRECTIONS[(i + 1) % HEX_DIRECTIONS.len()],
+                    ],
+                })
+                .collect(),
+            rotation: Rotation::Cycle,
+            conflict_resolution: ConflictResolution::Cancel,
+        }
+    }
+
+    // Cubic cells with 26 neighbors; an elf moves in one of six directions if the whole 3x3 layer of
+    // cells on that side is clear
+    fn cubic() -> Self {
+        let direction = |axis: usize, sign: i32| {
+            let mut step = [0; 3];
+            step[axis] = sign;
+
+            DirectionRule {
+                step: (step[0], step[1], step[2]),
+                clear: Rules::neighborhood(3)
+                    .into_iter()
+                    .filter(|&(x, y, z)| [x, y, z][axis] == sign)
+                    .collect(),
+            }
+        };
+
+        Rules {
+            neighborhood: Rules::neighborhood(3),
+            directions: vec![
+                direction(1, -1),
+                direction(1, 1),
+                direction(0, -1),
+                direction(0, 1),
+                direction(2, -1),
+                direction(2, 1),
+            ],
+            rotation: Rotation::Cycle,
+            conflict_resolution: ConflictResolution::Cancel,
+        }
+    }
+
+    // Every offset (except zero) within one step along each of the first `dimensions` axes
+    fn neighborhood(dimensions: usize) -> Vec<(i32, i32, i32)> {
+        let range = |axis: usize| if axis < dimensions { -1..=1 } else { 0..=0 };
+
+        range(2)
+            .flat_map(|z| range(1).flat_map(move |y| range(0).map(move |x| (x, y, z))))
+            .filter(|&offset| offset != (0, 0, 0))
+            .collect()
+    }
 }
 
 #[cfg(test)]
@@ -206,6 +837,180 @@ mod test {
         assert_eq!(110, grove.empty_ground_tiles());
     }
 
+    #[test]
+    fn test_small_grove() {
+        let mut grove = Grove::from_str(indoc! {"\
+            .....
+            ..##.
+            ..#..
+            .....
+            ..##.
+            .....
+        "})
+        .unwrap();
+
+        while grove.advance_round() {}
+
+        assert_eq!(
+            indoc! {"\
+                ..#..
+                ....#
+                #....
+                ....#
+                .....
+                ..#..
+            "},
+            grove.to_string()
+        );
+    }
+
+    #[test]
+    fn test_rules() {
+        // The elf at (2, 2) is crowded from the north and proposes moving south while the elf at
+        // (2, 4) proposes moving north into the same tile. With first-come conflict resolution,
+        // only the first of them in reading order moves; normally, neither would.
+        let small_grove = indoc! {"\
+            .....
+            ..##.
+            ..#..
+            .....
+            ..##.
+            .....
+        "};
+
+        let mut first_come = Grove::from_str(small_grove).unwrap().with_rules(Rules {
+            conflict_resolution: ConflictResolution::FirstCome,
+            ..Rules::standard()
+        });
+
+        let mut cancel = Grove::from_str(small_grove).unwrap();
+
+        first_come.advance_round();
+        cancel.advance_round();
+
+        assert_eq!(
+            HashSet::from([(2, 0, 0), (3, 0, 0), (2, 3, 0), (3, 3, 0), (2, 4, 0)]),
+            first_come.elves
+        );
+
+        assert_eq!(
+            HashSet::from([(2, 0, 0), (3, 0, 0), (2, 2, 0), (3, 3, 0), (2, 4, 0)]),
+            cancel.elves
+        );
+
+        // With a fixed direction order, both elves keep trying north first
+        let mut grove = Grove::from_str("##\n").unwrap().with_rules(Rules {
+            rotation: Rotation::Fixed,
+            ..Rules::standard()
+        });
+
+        grove.advance_round();
+        grove.advance_round();
+        assert_eq!(HashSet::from([(0, -2, 0), (1, -2, 0)]), grove.elves);
+
+        // Two hex neighbors move apart
+        let mut grove = Grove::from_str("##\n")
+            .unwrap()
+            .with_rules(Rules::hexagonal());
+
+        while grove.advance_round() {}
+        assert_eq!(1, grove.round);
+        assert_eq!(HashSet::from([(-1, 1, 0), (2, -1, 0)]), grove.elves);
+
+        // A stack of elves spreads out in three dimensions
+        let mut grove = Grove::from_str("#\n").unwrap().with_rules(Rules::cubic());
+        grove.elves.insert((0, 0, 1));
+        grove.elves.insert((0, 0, 2));
+
+        while grove.advance_round() {}
+
+        assert_eq!(3, grove.elves.len());
+        assert!(grove.elves.iter().all(|&(x, y, z)| {
+            grove
+                .elves
+                .iter()
+                .filter(|&&other| other != (x, y, z))
+                .all(|&(o_x, o_y, o_z)| {
+                    x.abs_diff(o_x) > 1 || y.abs_diff(o_y) > 1 || z.abs_diff(o_z) > 1
+                })
+        }));
+    }
+
+    #[test]
+    fn test_replay() {
+        let replay = Replay::record(Grove::from_str(TEST_GROVE).unwrap());
+
+        assert_eq!(20, replay.len());
+        assert_eq!(TEST_GROVE, replay.round(0).unwrap().to_string());
+        assert_eq!(110, replay.round(10).unwrap().empty_ground_tiles());
+        assert_eq!(19, replay.round(19).unwrap().round);
+        assert!(replay.round(20).is_none());
+    }
+
+    #[test]
+    fn test_pbm_frames() {
+        let replay = Replay::record(
+            Grove::from_str(indoc! {"\
+                ##
+                #.
+                ..
+                ##
+            "})
+            .unwrap(),
+        );
+
+        let frames = replay.pbm_frames();
+
+        assert_eq!(4, frames.len());
+        assert_eq!(
+            "P1\n5 6\n00000\n00110\n00100\n00000\n00110\n00000\n",
+            frames[0]
+        );
+        assert_eq!(
+            "P1\n5 6\n00100\n00001\n10000\n00001\n00000\n00100\n",
+            frames[3]
+        );
+    }
+
+    #[test]
+    fn test_dense_grove() {
+        let mut grove = Grove::from_str(TEST_GROVE).unwrap();
+        let mut dense_grove = DenseGrove::from_str(TEST_GROVE).unwrap();
+
+        assert_eq!(grove.elves, dense_grove.elves());
+
+        for _ in 0..10 {
+            assert_eq!(grove.advance_round(), dense_grove.advance_round());
+            assert_eq!(grove.elves, dense_grove.elves());
+        }
+
+        assert_eq!(110, dense_grove.empty_ground_tiles());
+
+        while grove.advance_round() {
+            assert!(dense_grove.advance_round());
+            assert_eq!(grove.elves, dense_grove.elves());
+        }
+
+        assert!(!dense_grove.advance_round());
+        assert_eq!(19, dense_grove.round);
+    }
+
+    #[test]
+    fn test_dense_grove_growth() {
+        // A crowded line of elves spreads out past the edges of its initial 64-tile word
+        let row = "#".repeat(70);
+        let input = [row.as_str(); 3].join("\n");
+
+        let mut grove = Grove::from_str(&input).unwrap();
+        let mut dense_grove = DenseGrove::from_str(&input).unwrap();
+
+        for _ in 0..50 {
+            assert_eq!(grove.advance_round(), dense_grove.advance_round());
+            assert_eq!(grove.elves, dense_grove.elves());
+            assert_eq!(grove.empty_ground_tiles(), dense_grove.empty_ground_tiles());
+        }
+    }
+
     #[test]
     fn test_rounds_until_settled() {
         let mut grove = Grove::from_str(TEST_GROVE).unwrap();
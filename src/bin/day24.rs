@@ -1,5 +1,8 @@
-use std::collections::{HashSet, LinkedList};
+use std::cmp::Reverse;
+use std::collections::hash_map::Entry;
+use std::collections::{BinaryHeap, HashMap};
 use std::error::Error;
+use std::fmt::{Display, Formatter};
 use std::fs;
 use std::str::FromStr;
 
@@ -7,23 +10,31 @@ fn main() -> Result<(), Box<dyn Error>> {
     let args: Vec<String> = std::env::args().collect();
 
     if let Some(path) = args.get(1) {
-        {
-            let valley = BlizzardValley::from_str(fs::read_to_string(path)?.as_str())?;
-            println!("Time to reach exit: {}", valley.fastest_time_to_exit());
-        }
+        let valley = BlizzardValley::from_str(fs::read_to_string(path)?.as_str())?;
 
-        {
-            let valley = BlizzardValley::from_str(fs::read_to_string(path)?.as_str())?;
+        println!("Time to reach exit: {}", valley.fastest_time_to_exit()?);
 
-            println!(
-                "Time to reach exit, then entrance, then exit: {}",
-                valley.fastest_time_to_exit_with_return_to_start()
-            );
+        println!(
+            "Time to reach exit, then entrance, then exit: {}",
+            valley.fastest_time_to_exit_with_return_to_start()?
+        );
+
+        if args.len() > 2 {
+            let waypoints = args[2..]
+                .iter()
+                .map(|waypoint| valley.waypoint(waypoint))
+                .collect::<Result<Vec<_>, _>>()?;
+
+            let route = valley.route(&waypoints)?;
+
+            println!();
+            print!("{}", route);
+            println!("Time to visit all waypoints: {}", route.moves.len());
         }
 
         Ok(())
     } else {
-        Err("Usage: day24 INPUT_FILE_PATH".into())
+        Err("Usage: day24 INPUT_FILE_PATH [WAYPOINT...]".into())
     }
 }
 
@@ -31,169 +42,300 @@ struct BlizzardValley {
     width: usize,
     height: usize,
 
-    elapsed_time: u32,
-
-    left_blizzards_by_row: Vec<LinkedList<bool>>,
-    right_blizzards_by_row: Vec<LinkedList<bool>>,
-    up_blizzards_by_col: Vec<LinkedList<bool>>,
-    down_blizzards_by_col: Vec<LinkedList<bool>>,
+    // Each bitset holds the positions of blizzards moving in the given direction at time 0
+    left_blizzards_by_row: Vec<Bitset>,
+    right_blizzards_by_row: Vec<Bitset>,
+    up_blizzards_by_col: Vec<Bitset>,
+    down_blizzards_by_col: Vec<Bitset>,
 }
 
 impl BlizzardValley {
-    fn fastest_time_to_exit(mut self) -> u32 {
-        let start = (0, 0);
-        let end = (self.width - 1, self.height - 1);
-
-        self.navigate(start, end);
+    // Positions are in map coordinates, so the entrance is in the top wall and the exit is in the
+    // bottom wall
+    fn entrance(&self) -> (usize, usize) {
+        (1, 0)
+    }
 
-        // We need one additional unit of time to step through the exit
-        self.elapsed_time + 1
+    fn exit(&self) -> (usize, usize) {
+        (self.width, self.height + 1)
     }
 
-    fn fastest_time_to_exit_with_return_to_start(mut self) -> u32 {
-        let start = (0, 0);
-        let end = (self.width - 1, self.height - 1);
+    // Parses "entrance", "exit", or an "x,y" position in map coordinates
+    fn waypoint(&self, string: &str) -> Result<(usize, usize), Box<dyn Error>> {
+        match string {
+            "entrance" => Ok(self.entrance()),
+            "exit" => Ok(self.exit()),
+            _ => {
+                let (x, y) = string
+                    .split_once(',')
+                    .ok_or_else(|| format!("Could not parse waypoint: {}", string))?;
+
+                Ok((x.parse()?, y.parse()?))
+            }
+        }
+    }
 
-        // Find a path from the start to the exit
-        self.navigate(start, end);
+    fn fastest_time_to_exit(&self) -> Result<u32, Box<dyn Error>> {
+        self.route(&[self.entrance(), self.exit()])
+            .map(|route| route.moves.len() as u32)
+    }
 
-        // Advance one unit of time while we step through the exit
-        self.advance_time();
+    fn fastest_time_to_exit_with_return_to_start(&self) -> Result<u32, Box<dyn Error>> {
+        self.route(&[self.entrance(), self.exit(), self.entrance(), self.exit()])
+            .map(|route| route.moves.len() as u32)
+    }
 
-        // Find a path back from the exit to the entrance
-        self.navigate(end, start);
+    // Finds the fastest route that starts at the first waypoint at time 0 and visits the rest of
+    // the waypoints in order
+    fn route(&self, waypoints: &[(usize, usize)]) -> Result<Route, Box<dyn Error>> {
+        let start = *waypoints
+            .first()
+            .ok_or("Route must have at least one waypoint")?;
 
-        // Step back out through the entrance
-        self.advance_time();
+        if let Some(waypoint) = waypoints
+            .iter()
+            .find(|&&waypoint| !self.is_open_tile(waypoint))
+        {
+            return Err(format!("Waypoint {:?} is not an open tile", waypoint).into());
+        }
 
-        // Find a path BACK to the exit
-        self.navigate(start, end);
+        if !self.is_clear(start, 0) {
+            return Err(format!("Starting waypoint {:?} has a blizzard at time 0", start).into());
+        }
 
-        // …and we need one additional unit of time to step through the exit
-        self.elapsed_time + 1
+        self.search(waypoints).map(|moves| Route { start, moves })
     }
 
-    fn navigate(&mut self, start: (usize, usize), end: (usize, usize)) {
-        let mut reachable_positions = HashSet::new();
+    // An A* search over (position, time, next waypoint) states. Because blizzards return to their
+    // starting positions every `lcm(width, height)` minutes, two states at the same position with
+    // the same next waypoint and the same phase of that cycle are equivalent, and only the earlier
+    // one needs to be explored. Waypoints are searched for together rather than one leg at a time
+    // because reaching an interior waypoint as early as possible doesn't always lead to the fastest
+    // route; a blizzard might push us somewhere worse than we'd be if we had arrived later.
+    fn search(&self, waypoints: &[(usize, usize)]) -> Result<Vec<Move>, Box<dyn Error>> {
+        let period = lcm(self.width, self.height);
 
-        loop {
-            if reachable_positions.contains(&end) {
-                break;
-            }
+        let distance = |a: (usize, usize), b: (usize, usize)| a.0.abs_diff(b.0) + a.1.abs_diff(b.1);
 
-            self.advance_time();
-            let empty_positions = self.empty_positions();
+        // The total distance between each waypoint and the last waypoint via all the waypoints in
+        // between
+        let mut remaining_distances = vec![0; waypoints.len()];
 
-            let mut next_reachable_positions = HashSet::new();
+        for i in (0..waypoints.len() - 1).rev() {
+            remaining_distances[i] =
+                remaining_distances[i + 1] + distance(waypoints[i], waypoints[i + 1]);
+        }
 
-            if empty_positions.contains(&start) {
-                next_reachable_positions.insert(start);
+        // Advances past any waypoints at the given position
+        let next_waypoint = |position: (usize, usize), mut next: usize| {
+            while next < waypoints.len() && waypoints[next] == position {
+                next += 1;
             }
 
-            for (x, y) in reachable_positions {
-                next_reachable_positions.extend(
-                    self.neighbors(x, y)
-                        .iter()
-                        .filter(|position| empty_positions.contains(position)),
-                );
+            next
+        };
+
+        let estimate = |position: (usize, usize), next: usize| {
+            if next == waypoints.len() {
+                0
+            } else {
+                distance(position, waypoints[next]) + remaining_distances[next]
             }
+        };
+
+        // Maps each explored (position, phase, next waypoint) to the move that first reached it and
+        // the next waypoint before that move
+        let mut came_from = HashMap::new();
+        let mut queue = BinaryHeap::new();
+
+        let start = waypoints[0];
+        let next = next_waypoint(start, 1);
+
+        came_from.insert((start, 0, next), (Move::Wait, next));
+        queue.push(Reverse((estimate(start, next), 0, start, next)));
+
+        while let Some(Reverse((_, time, position, next))) = queue.pop() {
+            if next == waypoints.len() {
+                let mut moves = vec![];
+                let (mut position, mut next) = (position, next);
+
+                for t in (1..=time).rev() {
+                    let (step, previous_next) = came_from[&(position, t % period, next)];
+
+                    moves.push(step);
+                    position = step.reverse(position);
+                    next = previous_next;
+                }
+
+                moves.reverse();
+                return Ok(moves);
+            }
+
+            for step in Move::ALL {
+                let Some(destination) = step.apply(position) else {
+                    continue;
+                };
+
+                let next_time = time + 1;
+
+                if !self.is_open_tile(destination) || !self.is_clear(destination, next_time) {
+                    continue;
+                }
+
+                let destination_next = next_waypoint(destination, next);
+                let key = (destination, next_time % period, destination_next);
+
+                if let Entry::Vacant(entry) = came_from.entry(key) {
+                    entry.insert((step, next));
 
-            reachable_positions = next_reachable_positions;
+                    queue.push(Reverse((
+                        next_time + estimate(destination, destination_next),
+                        next_time,
+                        destination,
+                        destination_next,
+                    )));
+                }
+            }
         }
+
+        Err(format!("No route visits all waypoints {:?}", waypoints).into())
     }
 
-    fn neighbors(&self, x: usize, y: usize) -> Vec<(usize, usize)> {
-        // Include the current position as a "neighbor" to explore
-        let mut neighbors = vec![(x, y)];
+    // Returns true if the given position is the entrance, the exit, or anywhere inside the walls
+    fn is_open_tile(&self, position: (usize, usize)) -> bool {
+        let (x, y) = position;
 
-        if x > 0 {
-            neighbors.push((x - 1, y));
-        }
+        position == self.entrance()
+            || position == self.exit()
+            || ((1..=self.width).contains(&x) && (1..=self.height).contains(&y))
+    }
 
-        if x < self.width - 1 {
-            neighbors.push((x + 1, y));
-        }
+    // Blizzards moving along a row or column just rotate that row or column's bitset by one bit
+    // per minute, so we can check whether a space is clear at any time by looking up the bit that
+    // would have rotated into it
+    fn is_clear(&self, position: (usize, usize), time: usize) -> bool {
+        let (x, y) = position;
 
-        if y > 0 {
-            neighbors.push((x, y - 1));
+        // Blizzards never reach the entrance or exit
+        if y == 0 || y == self.height + 1 {
+            return true;
         }
 
-        if y < self.height - 1 {
-            neighbors.push((x, y + 1));
+        let (x, y) = (x - 1, y - 1);
+        let (width, height) = (self.width, self.height);
+        let (x_shift, y_shift) = (time % width, time % height);
+
+        !(self.left_blizzards_by_row[y].contains((x + x_shift) % width)
+            || self.right_blizzards_by_row[y].contains((x + width - x_shift) % width)
+            || self.up_blizzards_by_col[x].contains((y + y_shift) % height)
+            || self.down_blizzards_by_col[x].contains((y + height - y_shift) % height))
+    }
+}
+
+// A fixed-length set of bits, stored 64 to a word
+#[derive(Clone)]
+struct Bitset {
+    words: Vec<u64>,
+}
+
+impl Bitset {
+    fn new(len: usize) -> Self {
+        Bitset {
+            words: vec![0; len.div_ceil(64)],
         }
+    }
 
-        neighbors
+    fn insert(&mut self, i: usize) {
+        self.words[i / 64] |= 1 << (i % 64);
     }
 
-    fn advance_time(&mut self) {
-        self.left_blizzards_by_row.iter_mut().for_each(|row| {
-            let cell = row.pop_front().unwrap();
-            row.push_back(cell);
-        });
+    fn contains(&self, i: usize) -> bool {
+        self.words[i / 64] & (1 << (i % 64)) != 0
+    }
+}
 
-        self.right_blizzards_by_row.iter_mut().for_each(|row| {
-            let cell = row.pop_back().unwrap();
-            row.push_front(cell);
-        });
+fn lcm(a: usize, b: usize) -> usize {
+    let gcd = |mut a: usize, mut b: usize| {
+        while b != 0 {
+            (a, b) = (b, a % b);
+        }
 
-        self.up_blizzards_by_col.iter_mut().for_each(|col| {
-            let cell = col.pop_front().unwrap();
-            col.push_back(cell);
-        });
+        a
+    };
 
-        self.down_blizzards_by_col.iter_mut().for_each(|col| {
-            let cell = col.pop_back().unwrap();
-            col.push_front(cell);
-        });
+    a / gcd(a, b) * b
+}
 
-        self.elapsed_time += 1;
-    }
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Move {
+    Wait,
+    Up,
+    Down,
+    Left,
+    Right,
+}
 
-    fn empty_positions(&self) -> HashSet<(usize, usize)> {
-        let mut spaces_with_blizzards = vec![false; self.width * self.height];
+impl Move {
+    const ALL: [Move; 5] = [Move::Wait, Move::Up, Move::Down, Move::Left, Move::Right];
 
-        for y in 0..self.height {
-            self.left_blizzards_by_row[y]
-                .iter()
-                .enumerate()
-                .filter(|(_, occupied)| **occupied)
-                .for_each(|(x, _)| {
-                    spaces_with_blizzards[(y * self.width) + x] = true;
-                });
+    fn apply(&self, (x, y): (usize, usize)) -> Option<(usize, usize)> {
+        match self {
+            Move::Wait => Some((x, y)),
+            Move::Up => Some((x, y.checked_sub(1)?)),
+            Move::Down => Some((x, y + 1)),
+            Move::Left => Some((x.checked_sub(1)?, y)),
+            Move::Right => Some((x + 1, y)),
+        }
+    }
 
-            self.right_blizzards_by_row[y]
-                .iter()
-                .enumerate()
-                .filter(|(_, occupied)| **occupied)
-                .for_each(|(x, _)| {
-                    spaces_with_blizzards[(y * self.width) + x] = true;
-                });
+    // Returns the position from which this move would reach the given position
+    fn reverse(&self, (x, y): (usize, usize)) -> (usize, usize) {
+        match self {
+            Move::Wait => (x, y),
+            Move::Up => (x, y + 1),
+            Move::Down => (x, y - 1),
+            Move::Left => (x + 1, y),
+            Move::Right => (x - 1, y),
         }
+    }
+}
 
-        for x in 0..self.width {
-            self.up_blizzards_by_col[x]
-                .iter()
-                .enumerate()
-                .filter(|(_, occupied)| **occupied)
-                .for_each(|(y, _)| {
-                    spaces_with_blizzards[(y * self.width) + x] = true;
-                });
+impl Display for Move {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Move::Wait => write!(f, "wait"),
+            Move::Up => write!(f, "move up"),
+            Move::Down => write!(f, "move down"),
+            Move::Left => write!(f, "move left"),
+            Move::Right => write!(f, "move right"),
+        }
+    }
+}
 
-            self.down_blizzards_by_col[x]
-                .iter()
-                .enumerate()
-                .filter(|(_, occupied)| **occupied)
-                .for_each(|(y, _)| {
-                    spaces_with_blizzards[(y * self.width) + x] = true;
-                });
+struct Route {
+    start: (usize, usize),
+    moves: Vec<Move>,
+}
+
+impl Display for Route {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mut position = self.start;
+
+        for (minute, step) in self.moves.iter().enumerate() {
+            position = step.apply(position).unwrap();
+            let preposition = if *step == Move::Wait { "at" } else { "to" };
+
+            writeln!(
+                f,
+                "Minute {}, {} {} {:?}",
+                minute + 1,
+                step,
+                preposition,
+                position
+            )?;
         }
 
-        spaces_with_blizzards
-            .iter()
-            .enumerate()
-            .filter(|(_, has_blizzard)| !**has_blizzard)
-            .map(|(i, _)| (i % self.width, i / self.width))
-            .collect()
+        Ok(())
     }
 }
 
@@ -206,24 +348,25 @@ impl FromStr for BlizzardValley {
 
         let mut y = 0;
 
-        let mut left_blizzards_by_row: Vec<LinkedList<bool>> = vec![LinkedList::new(); height];
-        let mut right_blizzards_by_row: Vec<LinkedList<bool>> = vec![LinkedList::new(); height];
-        let mut up_blizzards_by_col: Vec<LinkedList<bool>> = vec![LinkedList::new(); width];
-        let mut down_blizzards_by_col: Vec<LinkedList<bool>> = vec![LinkedList::new(); width];
+        let mut left_blizzards_by_row = vec![Bitset::new(width); height];
+        let mut right_blizzards_by_row = vec![Bitset::new(width); height];
+        let mut up_blizzards_by_col = vec![Bitset::new(height); width];
+        let mut down_blizzards_by_col = vec![Bitset::new(height); width];
 
         string
             .lines()
-            .skip_while(|line| line.starts_with("#."))
+            .skip(1)
             .take_while(|line| !line.starts_with("##"))
             .for_each(|line| {
                 line.chars()
                     .filter(|&c| c != '#')
                     .enumerate()
-                    .for_each(|(x, c)| {
-                        left_blizzards_by_row[y].push_back(c == '<');
-                        right_blizzards_by_row[y].push_back(c == '>');
-                        up_blizzards_by_col[x].push_back(c == '^');
-                        down_blizzards_by_col[x].push_back(c == 'v');
+                    .for_each(|(x, c)| match c {
+                        '<' => left_blizzards_by_row[y].insert(x),
+                        '>' => right_blizzards_by_row[y].insert(x),
+                        '^' => up_blizzards_by_col[x].insert(y),
+                        'v' => down_blizzards_by_col[x].insert(y),
+                        _ => {}
                     });
 
                 y += 1;
@@ -233,8 +376,6 @@ impl FromStr for BlizzardValley {
             width,
             height,
 
-            elapsed_time: 0,
-
             left_blizzards_by_row,
             right_blizzards_by_row,
             up_blizzards_by_col,
@@ -246,7 +387,9 @@ impl FromStr for BlizzardValley {
 #[cfg(test)]
 mod test {
     use super::*;
+    use advent_of_code_2022::xorshift::XorShift;
     use indoc::indoc;
+    use std::collections::HashSet;
 
     const TEST_VALLEY: &str = indoc! {"
         #.######
@@ -257,17 +400,22 @@ mod test {
         ######.#
     "};
 
+    fn empty_positions(valley: &BlizzardValley, time: usize) -> HashSet<(usize, usize)> {
+        (0..valley.height)
+            .flat_map(|y| (0..valley.width).map(move |x| (x, y)))
+            .filter(|&(x, y)| valley.is_clear((x + 1, y + 1), time))
+            .collect()
+    }
+
     #[test]
-    fn test_advance_time() {
-        let mut valley = BlizzardValley::from_str(TEST_VALLEY).unwrap();
+    fn test_is_clear() {
+        let valley = BlizzardValley::from_str(TEST_VALLEY).unwrap();
 
         {
             let expected_empty_positions = HashSet::from([(2, 0), (0, 1), (2, 1), (3, 1), (2, 2)]);
-            assert_eq!(expected_empty_positions, valley.empty_positions());
+            assert_eq!(expected_empty_positions, empty_positions(&valley, 0));
         }
 
-        valley.advance_time();
-
         {
             let expected_empty_positions = HashSet::from([
                 (0, 0),
@@ -282,19 +430,209 @@ mod test {
                 (3, 3),
             ]);
 
-            assert_eq!(expected_empty_positions, valley.empty_positions());
+            assert_eq!(expected_empty_positions, empty_positions(&valley, 1));
+        }
+    }
+
+    #[test]
+    fn test_is_clear_large_valley() {
+        let mut random = XorShift::new(0x2545f4914f6cdd1d);
+
+        let (width, height) = (150, 37);
+        let mut map = format!("#.{}\n", "#".repeat(width));
+        let mut blizzards = vec![];
+
+        for y in 0..height {
+            map.push('#');
+
+            for x in 0..width {
+                let c = ['<', '>', '^', 'v', '.', '.'][random.below(6) as usize];
+                map.push(c);
+
+                if c != '.' {
+                    blizzards.push((x, y, c));
+                }
+            }
+
+            map.push_str("#\n");
+        }
+
+        map.push_str(&format!("{}.#\n", "#".repeat(width)));
+
+        let valley = BlizzardValley::from_str(&map).unwrap();
+
+        for time in [0, 1, 64, 149, 150, 1000] {
+            let expected: HashSet<(usize, usize)> = blizzards
+                .iter()
+                .map(|&(x, y, c)| match c {
+                    '<' => ((x + width - (time % width)) % width, y),
+                    '>' => ((x + time) % width, y),
+                    '^' => (x, (y + height - (time % height)) % height),
+                    _ => (x, (y + time) % height),
+                })
+                .collect();
+
+            let empty = empty_positions(&valley, time);
+
+            let occupied: HashSet<(usize, usize)> = (0..height)
+                .flat_map(|y| (0..width).map(move |x| (x, y)))
+                .filter(|position| !empty.contains(position))
+                .collect();
+
+            assert_eq!(expected, occupied);
         }
     }
 
     #[test]
     fn test_fastest_time_to_exit() {
         let valley = BlizzardValley::from_str(TEST_VALLEY).unwrap();
-        assert_eq!(18, valley.fastest_time_to_exit());
+        assert_eq!(18, valley.fastest_time_to_exit().unwrap());
     }
 
     #[test]
     fn test_fastest_time_to_exit_with_return_to_start() {
         let valley = BlizzardValley::from_str(TEST_VALLEY).unwrap();
-        assert_eq!(54, valley.fastest_time_to_exit_with_return_to_start());
+        assert_eq!(
+            54,
+            valley.fastest_time_to_exit_with_return_to_start().unwrap()
+        );
+    }
+
+    // Checks that a route never leaves the valley or shares a space with a blizzard
+    fn assert_valid_route(valley: &BlizzardValley, route: &Route) {
+        let mut position = route.start;
+
+        for (minute, step) in route.moves.iter().enumerate() {
+            position = step.apply(position).unwrap();
+
+            assert!(valley.is_open_tile(position));
+            assert!(valley.is_clear(position, minute + 1));
+        }
+    }
+
+    fn visited_positions(route: &Route) -> Vec<(usize, usize)> {
+        route
+            .moves
+            .iter()
+            .scan(route.start, |position, step| {
+                *position = step.apply(*position).unwrap();
+                Some(*position)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_route() {
+        let valley = BlizzardValley::from_str(TEST_VALLEY).unwrap();
+
+        {
+            let route = valley.route(&[valley.entrance(), valley.exit()]).unwrap();
+
+            assert_eq!(18, route.moves.len());
+            assert_valid_route(&valley, &route);
+        }
+
+        {
+            let route = valley
+                .route(&[valley.entrance(), (6, 1), (1, 4), valley.exit()])
+                .unwrap();
+
+            assert_valid_route(&valley, &route);
+
+            let mut position = route.start;
+            let mut waypoints = vec![];
+
+            for step in &route.moves {
+                position = step.apply(position).unwrap();
+
+                if [(6, 1), (1, 4)].contains(&position) && waypoints.last() != Some(&position) {
+                    waypoints.push(position);
+                }
+            }
+
+            assert_eq!(valley.exit(), position);
+            assert_eq!(vec![(6, 1), (1, 4)], waypoints[..2]);
+        }
+
+        {
+            let route = valley.route(&[valley.exit()]).unwrap();
+            assert!(route.moves.is_empty());
+        }
+
+        assert!(valley.route(&[]).is_err());
+        assert!(valley.route(&[valley.entrance(), (0, 1)]).is_err());
+
+        // The top left corner of the valley has a blizzard at time 0
+        assert!(valley.route(&[(1, 1), valley.exit()]).is_err());
+    }
+
+    #[test]
+    fn test_route_interior_waypoint() {
+        // Reaching (3, 3) as early as possible (at minute 7) leaves us 11 minutes from the exit, but
+        // arriving later lets us get there in 15 minutes overall
+        {
+            let valley = BlizzardValley::from_str(indoc! {"
+                #.#####
+                #.^...#
+                #.^.<.#
+                #<>..<#
+                #####.#
+            "})
+            .unwrap();
+
+            let route = valley
+                .route(&[valley.entrance(), (3, 3), valley.exit()])
+                .unwrap();
+
+            assert_eq!(15, route.moves.len());
+            assert_valid_route(&valley, &route);
+            assert!(visited_positions(&route).contains(&(3, 3)));
+        }
+
+        // There's no way out after reaching (4, 1) as early as possible, but there is if we arrive
+        // later
+        {
+            let valley = BlizzardValley::from_str(indoc! {"
+                #.####
+                #<>>.#
+                #<...#
+                #..^.#
+                ####.#
+            "})
+            .unwrap();
+
+            let route = valley
+                .route(&[valley.entrance(), (4, 1), valley.exit()])
+                .unwrap();
+
+            assert_eq!(10, route.moves.len());
+            assert_valid_route(&valley, &route);
+            assert!(visited_positions(&route).contains(&(4, 1)));
+        }
+    }
+
+    #[test]
+    fn test_no_route() {
+        // Blizzards sweep across the only column of the valley every minute
+        let valley = BlizzardValley::from_str(indoc! {"
+            #.#
+            #v#
+            #^#
+            #.#
+        "})
+        .unwrap();
+
+        assert!(valley.fastest_time_to_exit().is_err());
+        assert!(valley.fastest_time_to_exit_with_return_to_start().is_err());
+    }
+
+    #[test]
+    fn test_waypoint() {
+        let valley = BlizzardValley::from_str(TEST_VALLEY).unwrap();
+
+        assert_eq!((1, 0), valley.waypoint("entrance").unwrap());
+        assert_eq!((6, 5), valley.waypoint("exit").unwrap());
+        assert_eq!((3, 2), valley.waypoint("3,2").unwrap());
+        assert!(valley.waypoint("3").is_err());
     }
 }
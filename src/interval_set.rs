@@ -0,0 +1,327 @@
+use std::slice::Iter;
+
+/// An inclusive range of integers, i.e. `start..=end`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Interval {
+    pub start: i64,
+    pub end: i64,
+}
+
+impl Interval {
+    pub fn new(start: i64, end: i64) -> Self {
+        assert!(start <= end, "Interval start must not be after its end");
+
+        Interval { start, end }
+    }
+
+    pub fn span(&self) -> u64 {
+        self.end.abs_diff(self.start) + 1
+    }
+
+    pub fn contains(&self, value: i64) -> bool {
+        value >= self.start && value <= self.end
+    }
+
+    pub fn overlaps(&self, other: &Self) -> bool {
+        other.end >= self.start && other.start <= self.end
+    }
+
+    pub fn intersection(&self, other: &Self) -> Option<Self> {
+        if self.overlaps(other) {
+            Some(Interval::new(
+                self.start.max(other.start),
+                self.end.min(other.end),
+            ))
+        } else {
+            None
+        }
+    }
+
+    // Overlapping intervals can obviously be merged, but so can intervals that merely touch (e.g.
+    // 1..=3 and 4..=6)
+    fn can_merge(&self, other: &Self) -> bool {
+        other.end.saturating_add(1) >= self.start && other.start <= self.end.saturating_add(1)
+    }
+}
+
+/// A set of integers stored as a sorted list of disjoint, non-adjacent intervals.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct IntervalSet {
+    intervals: Vec<Interval>,
+}
+
+impl IntervalSet {
+    pub fn new() -> Self {
+        IntervalSet { intervals: vec![] }
+    }
+
+    pub fn insert(&mut self, interval: Interval) {
+        // Find the run of existing intervals that overlap or touch the new interval, then replace
+        // that whole run with a single merged interval
+        let first = self
+            .intervals
+            .partition_point(|existing| existing.end.saturating_add(1) < interval.start);
+
+        let last = first
+            + self.intervals[first..]
+                .iter()
+                .take_while(|existing| existing.can_merge(&interval))
+                .count();
+
+        let merged = self.intervals[first..last]
+            .iter()
+            .fold(interval, |merged, existing| {
+                Interval::new(
+                    merged.start.min(existing.start),
+                    merged.end.max(existing.end),
+                )
+            });
+
+        self.intervals.splice(first..last, [merged]);
+    }
+
+    pub fn remove(&mut self, interval: Interval) {
+        let mut remaining = Vec::with_capacity(self.intervals.len() + 1);
+
+        for existing in &self.intervals {
+            if !existing.overlaps(&interval) {
+                remaining.push(*existing);
+                continue;
+            }
+
+            if existing.start < interval.start {
+                remaining.push(Interval::new(existing.start, interval.start - 1));
+            }
+
+            if existing.end > interval.end {
+                remaining.push(Interval::new(interval.end + 1, existing.end));
+            }
+        }
+
+        self.intervals = remaining;
+    }
+
+    pub fn contains(&self, value: i64) -> bool {
+        let index = self
+            .intervals
+            .partition_point(|interval| interval.end < value);
+
+        self.intervals
+            .get(index)
+            .map(|interval| interval.contains(value))
+            .unwrap_or(false)
+    }
+
+    pub fn is_superset(&self, other: &Self) -> bool {
+        other.intersection(self) == *other
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.intervals.is_empty()
+    }
+
+    /// Returns the total number of integers in this set.
+    pub fn len(&self) -> u64 {
+        self.intervals.iter().map(Interval::span).sum()
+    }
+
+    pub fn intervals(&self) -> Iter<'_, Interval> {
+        self.intervals.iter()
+    }
+
+    /// Returns the intervals between consecutive members of this set; values before the first
+    /// interval and after the last interval are not considered gaps.
+    pub fn gaps(&self) -> impl Iterator<Item = Interval> + '_ {
+        self.intervals
+            .windows(2)
+            .map(|pair| Interval::new(pair[0].end + 1, pair[1].start - 1))
+    }
+
+    pub fn union(&self, other: &Self) -> Self {
+        let mut union = self.clone();
+        other
+            .intervals()
+            .for_each(|interval| union.insert(*interval));
+
+        union
+    }
+
+    pub fn intersection(&self, other: &Self) -> Self {
+        let mut intervals = vec![];
+
+        let mut a = self.intervals.iter().peekable();
+        let mut b = other.intervals.iter().peekable();
+
+        while let (Some(interval_a), Some(interval_b)) = (a.peek(), b.peek()) {
+            if let Some(intersection) = interval_a.intersection(interval_b) {
+                intervals.push(intersection);
+            }
+
+            // Whichever interval ends first can't intersect with anything else in the other set
+            if interval_a.end < interval_b.end {
+                a.next();
+            } else {
+                b.next();
+            }
+        }
+
+        IntervalSet { intervals }
+    }
+
+    /// Returns every value within the given bounds that is not a member of this set.
+    pub fn complement(&self, bounds: Interval) -> Self {
+        let mut complement = IntervalSet::from(bounds);
+        self.intervals()
+            .for_each(|interval| complement.remove(*interval));
+
+        complement
+    }
+}
+
+impl From<Interval> for IntervalSet {
+    fn from(interval: Interval) -> Self {
+        IntervalSet {
+            intervals: vec![interval],
+        }
+    }
+}
+
+impl FromIterator<Interval> for IntervalSet {
+    fn from_iter<T: IntoIterator<Item = Interval>>(iter: T) -> Self {
+        let mut interval_set = IntervalSet::new();
+        iter.into_iter()
+            .for_each(|interval| interval_set.insert(interval));
+
+        interval_set
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn interval_set(intervals: &[(i64, i64)]) -> IntervalSet {
+        intervals
+            .iter()
+            .map(|(start, end)| Interval::new(*start, *end))
+            .collect()
+    }
+
+    #[test]
+    fn test_insert() {
+        let mut intervals = IntervalSet::new();
+
+        intervals.insert(Interval::new(10, 12));
+        intervals.insert(Interval::new(1, 3));
+        assert_eq!(interval_set(&[(1, 3), (10, 12)]), intervals);
+
+        // Adjacent intervals should be merged
+        intervals.insert(Interval::new(4, 5));
+        assert_eq!(interval_set(&[(1, 5), (10, 12)]), intervals);
+
+        // Overlapping intervals should be merged, even if they span several existing intervals
+        intervals.insert(Interval::new(20, 25));
+        intervals.insert(Interval::new(3, 21));
+        assert_eq!(
+            vec![Interval::new(1, 25)],
+            intervals.intervals().copied().collect::<Vec<_>>()
+        );
+
+        intervals.insert(Interval::new(2, 4));
+        assert_eq!(
+            vec![Interval::new(1, 25)],
+            intervals.intervals().copied().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut intervals = interval_set(&[(1, 10), (20, 30)]);
+
+        intervals.remove(Interval::new(4, 6));
+        assert_eq!(interval_set(&[(1, 3), (7, 10), (20, 30)]), intervals);
+
+        intervals.remove(Interval::new(8, 25));
+        assert_eq!(interval_set(&[(1, 3), (7, 7), (26, 30)]), intervals);
+
+        intervals.remove(Interval::new(-100, 100));
+        assert!(intervals.is_empty());
+    }
+
+    #[test]
+    fn test_contains() {
+        let intervals = interval_set(&[(-5, -1), (3, 4)]);
+
+        assert!(intervals.contains(-5));
+        assert!(intervals.contains(-1));
+        assert!(intervals.contains(4));
+        assert!(!intervals.contains(0));
+        assert!(!intervals.contains(5));
+        assert!(!intervals.contains(-6));
+    }
+
+    #[test]
+    fn test_is_superset() {
+        let intervals = interval_set(&[(2, 8), (12, 14)]);
+
+        assert!(intervals.is_superset(&interval_set(&[(3, 7)])));
+        assert!(intervals.is_superset(&interval_set(&[(2, 2), (12, 14)])));
+        assert!(!intervals.is_superset(&interval_set(&[(7, 12)])));
+        assert!(intervals.is_superset(&IntervalSet::new()));
+    }
+
+    #[test]
+    fn test_len() {
+        assert_eq!(0, IntervalSet::new().len());
+        assert_eq!(14, interval_set(&[(-2, 8), (12, 14)]).len());
+    }
+
+    #[test]
+    fn test_gaps() {
+        assert_eq!(
+            vec![Interval::new(9, 11), Interval::new(16, 16)],
+            interval_set(&[(-2, 8), (12, 15), (17, 20)])
+                .gaps()
+                .collect::<Vec<_>>()
+        );
+
+        assert_eq!(0, interval_set(&[(1, 4)]).gaps().count());
+    }
+
+    #[test]
+    fn test_union() {
+        assert_eq!(
+            interval_set(&[(1, 8), (12, 14)]),
+            interval_set(&[(1, 3), (12, 14)]).union(&interval_set(&[(4, 8)]))
+        );
+    }
+
+    #[test]
+    fn test_intersection() {
+        assert_eq!(
+            interval_set(&[(3, 5), (8, 8), (12, 13)]),
+            interval_set(&[(1, 5), (8, 13)]).intersection(&interval_set(&[
+                (3, 6),
+                (7, 8),
+                (12, 20)
+            ]))
+        );
+
+        assert!(interval_set(&[(1, 5)])
+            .intersection(&interval_set(&[(6, 9)]))
+            .is_empty());
+    }
+
+    #[test]
+    fn test_complement() {
+        assert_eq!(
+            interval_set(&[(0, 0), (6, 7), (10, 10)]),
+            interval_set(&[(-3, -1), (1, 5), (8, 9), (11, 12)]).complement(Interval::new(-1, 10))
+        );
+
+        assert_eq!(
+            interval_set(&[(0, 20)]),
+            IntervalSet::new().complement(Interval::new(0, 20))
+        );
+    }
+}
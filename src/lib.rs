@@ -0,0 +1,2 @@
+pub mod interval_set;
+pub mod xorshift;
@@ -0,0 +1,44 @@
+/// A small, deterministic pseudo-random number generator (Marsaglia's xorshift64) for building
+/// reproducible test cases without pulling in another dependency.
+pub struct XorShift {
+    state: u64,
+}
+
+impl XorShift {
+    pub fn new(seed: u64) -> Self {
+        assert_ne!(0, seed, "Seed must not be zero");
+
+        XorShift { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+
+        self.state
+    }
+
+    /// Returns a value in `0..bound`.
+    pub fn below(&mut self, bound: u64) -> u64 {
+        self.next_u64() % bound
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_reproducible() {
+        let mut a = XorShift::new(0x2545f4914f6cdd1d);
+        let mut b = XorShift::new(0x2545f4914f6cdd1d);
+
+        for _ in 0..100 {
+            let value = a.below(10);
+
+            assert!(value < 10);
+            assert_eq!(value, b.below(10));
+        }
+    }
+}